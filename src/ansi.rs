@@ -0,0 +1,350 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// One of the 256 palette colors; 0-15 are the basic and bright ones.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// Graphic rendition set by SGR (`ESC [ ... m`) sequences.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub blink: bool,
+    pub reverse: bool,
+    pub hidden: bool,
+    pub strike: bool,
+}
+
+impl Style {
+    pub fn is_default(&self) -> bool {
+        *self == Style::default()
+    }
+
+    /// Updates the style from the parameters of one SGR sequence.
+    pub fn apply_sgr(&mut self, params: &[u16]) {
+        if params.is_empty() {
+            *self = Style::default();
+            return;
+        }
+        let mut iter = params.iter().copied();
+        while let Some(param) = iter.next() {
+            match param {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                5 | 6 => self.blink = true,
+                7 => self.reverse = true,
+                8 => self.hidden = true,
+                9 => self.strike = true,
+                21 => self.underline = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                25 => self.blink = false,
+                27 => self.reverse = false,
+                28 => self.hidden = false,
+                29 => self.strike = false,
+                30..=37 => self.fg = Some(Color::Indexed((param - 30) as u8)),
+                38 => self.fg = extended_color(&mut iter),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(Color::Indexed((param - 40) as u8)),
+                48 => self.bg = extended_color(&mut iter),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(Color::Indexed((param - 90 + 8) as u8)),
+                100..=107 => self.bg = Some(Color::Indexed((param - 100 + 8) as u8)),
+                _ => {}
+            }
+        }
+    }
+
+    /// SGR parameters that produce this style from the default one.
+    fn params(&self) -> String {
+        let mut params = Vec::new();
+        for (on, code) in [
+            (self.bold, "1"),
+            (self.dim, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+            (self.blink, "5"),
+            (self.reverse, "7"),
+            (self.hidden, "8"),
+            (self.strike, "9"),
+        ] {
+            if on {
+                params.push(code.to_string());
+            }
+        }
+        for (color, base) in [(self.fg, 30), (self.bg, 40)] {
+            match color {
+                Some(Color::Indexed(idx)) if idx < 8 => params.push((base + idx as u16).to_string()),
+                Some(Color::Indexed(idx)) if idx < 16 => {
+                    params.push((base + 60 + idx as u16 - 8).to_string())
+                }
+                Some(Color::Indexed(idx)) => params.push(format!("{};5;{idx}", base + 8)),
+                Some(Color::Rgb(r, g, b)) => params.push(format!("{};2;{r};{g};{b}", base + 8)),
+                None => {}
+            }
+        }
+        params.join(";")
+    }
+
+    /// The shortest sequence switching from `from` to this style.
+    pub fn transition(&self, from: &Style) -> String {
+        if self == from {
+            String::new()
+        } else if self.is_default() {
+            "\x1b[0m".to_string()
+        } else if from.is_default() {
+            format!("\x1b[{}m", self.params())
+        } else {
+            format!("\x1b[0;{}m", self.params())
+        }
+    }
+}
+
+/// Reads the color after a `38` or `48` parameter: `5;n` or `2;r;g;b`.
+fn extended_color(iter: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match iter.next()? {
+        5 => Some(Color::Indexed(iter.next()?.min(255) as u8)),
+        2 => {
+            let mut channel = || iter.next().map(|value| value.min(255) as u8);
+            Some(Color::Rgb(channel()?, channel()?, channel()?))
+        }
+        _ => None,
+    }
+}
+
+/// What the parser makes of one character of input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnsiAction {
+    /// A character to show.
+    Print(char),
+    /// A C0 control such as `\n`, `\r`, `\t` or backspace.
+    Control(char),
+    /// A complete SGR sequence and its parameters.
+    Sgr(Vec<u16>),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    /// Inside `ESC (`, `ESC )` and friends, which take one more character;
+    /// `true` when designating G0, the set in use.
+    Charset(bool),
+    Csi,
+    /// OSC, DCS, SOS, PM and APC strings, which run until BEL or ST.
+    Str,
+    /// Saw `ESC` inside a string; a following `\` ends it.
+    StrEscape,
+}
+
+/// Escape sequence parser following the state machine terminals use, so
+/// that every sequence is consumed whole. Only SGR is reported; cursor
+/// movement, OSC (titles, hyperlinks) and device strings are dropped.
+/// Selecting the DEC line drawing set maps its characters to box drawing.
+#[derive(Debug, Clone, Default)]
+pub struct Parser {
+    state: State,
+    params: String,
+    line_drawing: bool,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, ch: char) -> Option<AnsiAction> {
+        match self.state {
+            State::Ground => self.ground(ch),
+            State::Escape => {
+                self.state = match ch {
+                    '[' => {
+                        self.params.clear();
+                        State::Csi
+                    }
+                    ']' | 'P' | 'X' | '^' | '_' => State::Str,
+                    '(' => State::Charset(true),
+                    ')' | '*' | '+' | '-' | '.' | '/' | '#' | '%' => State::Charset(false),
+                    '\x1b' => State::Escape,
+                    _ => State::Ground,
+                };
+                None
+            }
+            State::Charset(g0) => {
+                if g0 {
+                    self.line_drawing = ch == '0';
+                }
+                self.state = State::Ground;
+                None
+            }
+            State::Csi => {
+                match ch {
+                    '\x40'..='\x7e' => {
+                        self.state = State::Ground;
+                        if ch == 'm' {
+                            return Some(AnsiAction::Sgr(sgr_params(&self.params)));
+                        }
+                    }
+                    '\x1b' => self.state = State::Escape,
+                    '\x18' | '\x1a' => self.state = State::Ground,
+                    _ => self.params.push(ch),
+                }
+                None
+            }
+            State::Str => {
+                match ch {
+                    '\x07' | '\u{9c}' => self.state = State::Ground,
+                    '\x1b' => self.state = State::StrEscape,
+                    _ => {}
+                }
+                None
+            }
+            State::StrEscape => {
+                self.state = if ch == '\\' { State::Ground } else { State::Str };
+                None
+            }
+        }
+    }
+
+    fn ground(&mut self, ch: char) -> Option<AnsiAction> {
+        match ch {
+            '\x1b' => {
+                self.state = State::Escape;
+                None
+            }
+            '\u{9b}' => {
+                self.params.clear();
+                self.state = State::Csi;
+                None
+            }
+            '\u{90}' | '\u{98}' | '\u{9d}' | '\u{9e}' | '\u{9f}' => {
+                self.state = State::Str;
+                None
+            }
+            '\0'..='\x1f' | '\x7f' => Some(AnsiAction::Control(ch)),
+            '\u{80}'..='\u{9f}' => None,
+            _ if self.line_drawing => Some(AnsiAction::Print(line_drawing(ch))),
+            _ => Some(AnsiAction::Print(ch)),
+        }
+    }
+}
+
+/// Maps a character of the DEC special graphics set to Unicode.
+fn line_drawing(ch: char) -> char {
+    const TABLE: &str = " ◆▒␉␌␍␊°±␤␋┘┐┌└┼⎺⎻─⎼⎽├┤┴┬│≤≥π≠£·";
+    match ch {
+        '_'..='~' => TABLE
+            .chars()
+            .nth(ch as usize - '_' as usize)
+            .unwrap_or(ch),
+        _ => ch,
+    }
+}
+
+/// Flattens SGR parameters into the `;` form `apply_sgr` reads. A `:`
+/// group is one parameter with sub-parameters, e.g. `38:2::R:G:B` with an
+/// empty colour space, or `4:3` for a curly underline.
+fn sgr_params(raw: &str) -> Vec<u16> {
+    if raw.is_empty() {
+        return Vec::new();
+    }
+    let mut params = Vec::new();
+    for group in raw.split(';') {
+        let fields: Vec<u16> = group
+            .split(':')
+            .map(|field| field.parse().unwrap_or(0))
+            .collect();
+        match fields.as_slice() {
+            [only] => params.push(*only),
+            // Underline colour, which is not drawn.
+            [58, ..] => {}
+            [code @ (38 | 48), 2, rest @ ..] => {
+                // The colour space comes first when all of it is given.
+                let rgb = if rest.len() > 3 { &rest[1..] } else { rest };
+                params.extend([*code, 2]);
+                params.extend((0..3).map(|idx| rgb.get(idx).copied().unwrap_or(0)));
+            }
+            [code @ (38 | 48), 5, index, ..] => params.extend([*code, 5, *index]),
+            [4, style, ..] => params.push(if *style == 0 { 24 } else { 4 }),
+            [first, ..] => params.push(*first),
+            [] => {}
+        }
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printed(input: &str) -> String {
+        let mut parser = Parser::new();
+        input
+            .chars()
+            .filter_map(|ch| match parser.feed(ch) {
+                Some(AnsiAction::Print(ch)) => Some(ch),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn applies_sgr_colors_and_attributes() {
+        let mut style = Style::default();
+        style.apply_sgr(&[1, 31]);
+        assert!(style.bold);
+        assert_eq!(style.fg, Some(Color::Indexed(1)));
+        style.apply_sgr(&[38, 5, 200, 48, 2, 1, 2, 3]);
+        assert_eq!(style.fg, Some(Color::Indexed(200)));
+        assert_eq!(style.bg, Some(Color::Rgb(1, 2, 3)));
+        style.apply_sgr(&[]);
+        assert!(style.is_default());
+    }
+
+    #[test]
+    fn reads_colon_groups_as_one_parameter() {
+        assert_eq!(sgr_params("1;31"), vec![1, 31]);
+        assert_eq!(sgr_params("38:2::10:20:30;1"), vec![38, 2, 10, 20, 30, 1]);
+        assert_eq!(sgr_params("48:2:10:20:30"), vec![48, 2, 10, 20, 30]);
+        assert_eq!(sgr_params("38:5:200"), vec![38, 5, 200]);
+        assert_eq!(sgr_params("4:3;58:2::1:2:3;4:0"), vec![4, 24]);
+    }
+
+    #[test]
+    fn transitions_are_minimal() {
+        let green = Style {
+            fg: Some(Color::Indexed(2)),
+            ..Style::default()
+        };
+        let reversed = Style {
+            reverse: true,
+            ..green
+        };
+        assert_eq!(green.transition(&Style::default()), "\x1b[32m");
+        assert_eq!(reversed.transition(&green), "\x1b[0;7;32m");
+        assert_eq!(Style::default().transition(&green), "\x1b[0m");
+    }
+
+    #[test]
+    fn drops_non_sgr_sequences_whole() {
+        assert_eq!(printed("a\x1b[2Kb\x1b[10;5Hc"), "abc");
+        assert_eq!(
+            printed("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x07!"),
+            "link!"
+        );
+        assert_eq!(printed("\x1b(0lq\x1b(Bq\x1b7x\x1bPdata\x1b\\y"), "┌─qxy");
+    }
+}
@@ -1,22 +1,43 @@
-use std::io::{self, Write};
-use std::time::Instant;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use chrono::Local;
-use crossterm::cursor::MoveTo;
-use crossterm::terminal::{Clear, ClearType};
-use crossterm::execute;
 
-use crate::config::{ColorMode, Config};
+use crate::condition::{any_fires, RunEvent};
+use crate::config::{ColorMode, Config, DifferencesMode};
+use crate::ansi::Style;
 use crate::diff::DiffState;
-use crate::exec::{build_command, run_command, ExecError};
-use crate::render::{format_text, header_line, strip_ansi_text, terminal_size};
-use crate::screenshot::save_screenshot;
+use crate::exec::{
+    build_command, run_command, run_command_with_input, select_shell, set_run_env, ExecError,
+    ExecOptions,
+};
+use crate::filter::{Filter, FilterChecks};
+use crate::follow::FollowLog;
+use crate::grid::{Grid, Line};
+use crate::help::{help_lines, overlay};
+use crate::hooks::{HookContext, HookEvent, Hooks, HOOK_EXIT_WAIT};
+use crate::input::{edit_prompt, Action, PromptKey, Scroll};
+use crate::keymap::KeyReader;
+use crate::notify::Notifier;
+use crate::pane::{pane_areas, PaneCommand, SplitDirection};
+use crate::pattern::Pattern;
+use crate::render::{
+    divider_line, header_line, join_columns, pane_header, terminal_size, toast_line, TerminalSize,
+};
+use crate::schedule::{Pacer, Schedule};
+use crate::screen::Screen;
+use crate::screenshot::{prune_screenshots, save_screenshot};
+use crate::search::Search;
 use crate::terminal::{wait_for_action, wait_for_keypress, TerminalGuard, WaitOutcome};
+use crate::watcher::{FileWatcher, WatchError};
 
 #[derive(Debug)]
 pub enum AppError {
     Io(io::Error),
     Exec(ExecError),
+    Watch(WatchError),
 }
 
 impl std::fmt::Display for AppError {
@@ -24,6 +45,7 @@ impl std::fmt::Display for AppError {
         match self {
             AppError::Io(err) => write!(f, "io error: {err}"),
             AppError::Exec(err) => write!(f, "{err}"),
+            AppError::Watch(err) => write!(f, "{err}"),
         }
     }
 }
@@ -42,130 +64,859 @@ impl From<ExecError> for AppError {
     }
 }
 
-pub fn run(config: Config) -> Result<i32, AppError> {
-    let _terminal = TerminalGuard::new()?;
-    let mut stdout = io::stdout();
-    let mut diff_state = DiffState::new();
-    let mut last_visible: Option<String> = None;
-    let mut unchanged_cycles = 0u32;
-    let mut pending_screenshot = false;
-    let mut next_run = Instant::now();
-    let mut first = true;
+impl From<WatchError> for AppError {
+    fn from(err: WatchError) -> Self {
+        AppError::Watch(err)
+    }
+}
 
-    loop {
-        if !first {
-            let wait = if config.precise {
-                next_run.saturating_duration_since(Instant::now())
-            } else {
-                config.interval
-            };
-            match wait_for_action(wait, config.no_rerun, &mut pending_screenshot)? {
-                WaitOutcome::Quit => return Ok(0),
-                WaitOutcome::Trigger => {}
-                WaitOutcome::Timeout => {}
-            }
+/// One watched command and everything tracked between its runs.
+struct Pane {
+    command: Vec<String>,
+    schedule: Schedule,
+    pacer: Pacer,
+    diff_state: DiffState,
+    /// Output of the last run with diff highlights; wrapped when drawn.
+    grid: Grid,
+    /// Plain output of the last run, handed to hooks as the old output.
+    output: String,
+    last_success: Option<bool>,
+    /// Every run's output in `--follow` mode.
+    log: FollowLog,
+    scroll: usize,
+    /// Whether the view sits at the bottom and keeps following new output.
+    tail: bool,
+    /// Rows the view moved down by at the last draw.
+    shift: usize,
+    /// The last output before any diff highlighting.
+    raw: Grid,
+    /// The text change detection last looked at, when it is the filtered
+    /// output rather than the diff's.
+    checked: Option<String>,
+    /// The row each search match starts on, as of the last draw.
+    matches: Vec<usize>,
+    /// Output rows shown at the last draw, used for paging.
+    page: usize,
+    /// The output `--chgexit` and `--equexit` compared last.
+    last_checked: Option<String>,
+    unchanged_cycles: u32,
+    run_number: u64,
+    prev_exit: Option<i32>,
+    next_run: Instant,
+    run_started: Instant,
+    run_finished: Instant,
+}
+
+impl Pane {
+    fn new(config: &Config, pane: &PaneCommand, now: Instant) -> Self {
+        Self {
+            command: pane.command.clone(),
+            schedule: Schedule::from_config(config).with_interval(pane.interval),
+            pacer: Pacer::from_config(config),
+            diff_state: DiffState::new(),
+            grid: Grid::default(),
+            raw: Grid::default(),
+            output: String::new(),
+            last_success: None,
+            log: FollowLog::new(config.separator, config.dedup),
+            scroll: 0,
+            tail: true,
+            shift: 0,
+            checked: None,
+            matches: Vec::new(),
+            page: 1,
+            last_checked: None,
+            unchanged_cycles: 0,
+            run_number: 0,
+            prev_exit: None,
+            next_run: now,
+            run_started: now,
+            run_finished: now,
         }
+    }
 
-        first = false;
-        let run_started = Instant::now();
-        let exec_output = run_command(build_command(&config.command, config.exec)?)?;
-        if config.beep && !exec_output.status.success() {
-            stdout.write_all(b"\x07")?;
+    fn scroll(&mut self, scroll: Scroll) {
+        if matches!(scroll, Scroll::Up | Scroll::PageUp | Scroll::Top) {
+            self.tail = false;
         }
+        self.scroll = match scroll {
+            Scroll::Up => self.scroll.saturating_sub(1),
+            Scroll::Down => self.scroll.saturating_add(1),
+            Scroll::PageUp => self.scroll.saturating_sub(self.page),
+            Scroll::PageDown => self.scroll.saturating_add(self.page),
+            Scroll::Top => 0,
+            Scroll::Bottom => usize::MAX,
+        };
+    }
+
+    /// Scrolls so that `row` is at the top, or as near as it gets.
+    fn scroll_to(&mut self, row: usize) {
+        self.tail = false;
+        self.scroll = row;
+    }
 
-        let mut text = String::from_utf8_lossy(&exec_output.combined()).into_owned();
-        if matches!(config.color, ColorMode::Auto | ColorMode::Never) {
-            text = strip_ansi_text(&text);
+    /// The output as screen rows `columns` wide, keeping only the lines
+    /// the filter lets through and with any search matches highlighted.
+    fn rows(
+        &mut self,
+        config: &Config,
+        filter: Option<&Filter>,
+        search: Option<&Search>,
+        columns: usize,
+    ) -> Vec<Line> {
+        let kept;
+        let grid = if config.follow {
+            kept = self.log.grid(columns, filter);
+            &kept
+        } else if let Some(filter) = filter {
+            kept = filter.apply(&self.grid);
+            &kept
+        } else {
+            &self.grid
+        };
+        match search {
+            Some(search) => {
+                let (rows, matches) = search.apply(grid, columns, config.no_wrap);
+                self.matches = matches;
+                rows
+            }
+            None => {
+                self.matches.clear();
+                grid.wrap(columns, config.no_wrap)
+            }
         }
+    }
 
-        let diff_result = diff_state.apply(&text, config.differences);
-        let size = terminal_size();
-        let mut output_lines = format_text(&diff_result.text, size.columns, config.no_wrap);
+    /// Makes the last output, seen through the new `filter`, what the
+    /// next run is compared against, so that changing the filter does not
+    /// count as a change in the output.
+    fn rebase_checks(&mut self, filter: Option<&Filter>) {
+        if self.run_number == 0 {
+            return;
+        }
+        let text = match filter {
+            Some(filter) => filter.apply(&self.raw).text(),
+            None => self.raw.text(),
+        };
+        self.checked = filter.map(|_| text.clone());
+        if self.last_checked.is_some() {
+            self.last_checked = Some(text.clone());
+        }
+        self.output = text;
+    }
 
-        let mut frame = Vec::new();
-        let header_lines = if config.no_title { 0 } else { 2 };
-        if !config.no_title {
-            frame.push(header_line(
-                &config.command,
-                config.interval,
-                Local::now(),
-                size.columns,
-            ));
-            frame.push(String::new());
+    /// Clamps the scroll offset and returns the lines that fit in `rows`.
+    /// With `follow`, a view at the bottom stays there as lines are added.
+    fn visible_lines(&mut self, lines: Vec<Line>, rows: usize, follow: bool) -> Vec<Line> {
+        let previous = self.scroll;
+        let bottom = lines.len().saturating_sub(rows);
+        if follow && self.tail {
+            self.scroll = bottom;
         }
+        self.page = rows.max(1);
+        self.scroll = self.scroll.min(bottom);
+        self.tail = self.scroll == bottom;
+        self.shift = self.scroll.saturating_sub(previous);
+        lines.into_iter().skip(self.scroll).take(rows).collect()
+    }
+}
+
+/// How the panes are shown; changed by keys, never by runs.
+#[derive(Debug, Default)]
+struct View {
+    focus: usize,
+    zoomed: bool,
+    search: Option<Search>,
+    filter: Option<Filter>,
+    /// What is being typed after `/` or `&`, shown on the bottom row.
+    prompt: Option<Prompt>,
+    /// Whether the help is drawn over the panes; the next key closes it.
+    help: bool,
+    /// No runs are started on schedule while paused.
+    paused: bool,
+    /// The diff mode, which `toggle-diff` turns off and back on.
+    differences: Option<DifferencesMode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptKind {
+    Search,
+    Filter,
+}
 
-        if !config.follow {
-            let available = size.rows.saturating_sub(header_lines as u16) as usize;
-            output_lines.truncate(available);
+#[derive(Debug)]
+struct Prompt {
+    kind: PromptKind,
+    text: String,
+}
+
+impl Prompt {
+    fn new(kind: PromptKind) -> Self {
+        Self {
+            kind,
+            text: String::new(),
         }
+    }
+}
 
-        frame.extend(output_lines);
+/// What a run produced, checked once the frame is on screen.
+struct RunReport {
+    pane: usize,
+    success: bool,
+    code: Option<i32>,
+    changed: bool,
+    plain: String,
+}
 
-        if config.follow {
-            write_frame(&mut stdout, &frame)?;
+pub fn run(config: Config) -> Result<i32, AppError> {
+    let mut watcher = if config.watch_paths.is_empty() {
+        None
+    } else {
+        Some(FileWatcher::new(
+            &config.watch_paths,
+            &config.watch_excludes,
+            config.watch_debounce,
+        )?)
+    };
+    let exec_options = ExecOptions {
+        shell: if config.exec {
+            None
         } else {
-            execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
-            write_frame(&mut stdout, &frame)?;
+            Some(select_shell(config.shell.as_deref(), &config.shell_args)?)
+        },
+        chdir: config.chdir.clone(),
+        env: config.env.clone(),
+        env_clear: config.env_clear,
+    };
+    // Hooks always go through a shell, even with --exec.
+    let hook_options = ExecOptions {
+        shell: match &exec_options.shell {
+            Some(shell) => Some(shell.clone()),
+            None => Some(select_shell(None, &[])?),
+        },
+        ..exec_options.clone()
+    };
+    let mut hooks = Hooks::new(config.hooks.clone(), hook_options, config.hook_gap);
+    // Our own stdin can only be read once, and must be drained before the
+    // terminal switches to raw mode.
+    let piped_stdin = match config.stdin.as_deref() {
+        Some(path) if path == Path::new("-") => {
+            let mut input = Vec::new();
+            io::stdin()
+                .read_to_end(&mut input)
+                .map_err(|err| ExecError::StdinFailed(err.to_string()))?;
+            Some(input)
+        }
+        _ => None,
+    };
+    let _terminal = TerminalGuard::new()?;
+    let mut stdout = io::stdout();
+    let mut screen = Screen::new();
+    let mut notifier = Notifier::new(config.notify.clone(), config.notify_command.clone());
+    let started = Instant::now();
+    let mut panes: Vec<Pane> = config
+        .panes
+        .iter()
+        .map(|pane| Pane::new(&config, pane, started))
+        .collect();
+    let mut view = View {
+        filter: config.filter.clone(),
+        differences: config.differences,
+        ..View::default()
+    };
+    let mut keys = KeyReader::new(config.keymap.clone());
+    let mut last_frame: Vec<Line> = Vec::new();
+    let mut toast_until: Option<Instant> = None;
+    let mut last_bell: Option<Instant> = None;
+    let mut first = true;
+
+    loop {
+        let due: Vec<usize> = if first {
+            (0..panes.len()).collect()
+        } else {
+            loop {
+                let next_run = match view.paused {
+                    true => Instant::now() + PAUSED_POLL,
+                    false => panes.iter().map(|pane| pane.next_run).min().unwrap_or(started),
+                };
+                let deadline = toast_until.map_or(next_run, |until| until.min(next_run));
+                let raw_keys = view.prompt.is_some() || view.help;
+                // File changes are left for after a pause.
+                let watcher = watcher.as_mut().filter(|_| !view.paused);
+                match wait_for_action(deadline, watcher, &mut keys, raw_keys)? {
+                    WaitOutcome::Action(Action::Quit) => return Ok(0),
+                    WaitOutcome::Action(Action::Trigger) | WaitOutcome::FilesChanged => {
+                        break (0..panes.len()).collect();
+                    }
+                    WaitOutcome::Action(Action::Screenshot) => {
+                        let message = match config.shotsdir.as_deref() {
+                            Some(dir) => match save_frame(dir, config.shots_keep, &last_frame) {
+                                Ok(path) => format!("saved screenshot to {}", path.display()),
+                                Err(err) => format!("screenshot failed: {err}"),
+                            },
+                            None => "screenshot failed: no --shotsdir given".to_string(),
+                        };
+                        show_toast(&mut stdout, &mut screen, &message)?;
+                        toast_until = Some(Instant::now() + TOAST_DURATION);
+                    }
+                    WaitOutcome::Action(
+                        action @ (Action::IncreaseInterval | Action::DecreaseInterval),
+                    ) => {
+                        let factor = match action {
+                            Action::IncreaseInterval => 2.0,
+                            _ => 0.5,
+                        };
+                        let pane = &mut panes[view.focus];
+                        if !pane.schedule.scale(factor) {
+                            let message = match pane.schedule {
+                                Schedule::OnChange => "interval cannot be changed when running on file changes",
+                                _ => "interval cannot be changed with --cron",
+                            };
+                            show_toast(&mut stdout, &mut screen, message)?;
+                            toast_until = Some(Instant::now() + TOAST_DURATION);
+                            continue;
+                        }
+                        pane.next_run = pane.schedule.next_run(
+                            pane.run_started,
+                            pane.run_finished,
+                            &mut pane.pacer,
+                        );
+                        if config.no_title {
+                            show_toast(&mut stdout, &mut screen, &pane.schedule.label(&pane.pacer))?;
+                            toast_until = Some(Instant::now() + TOAST_DURATION);
+                        } else {
+                            last_frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                        }
+                    }
+                    WaitOutcome::Action(Action::Pause) => {
+                        view.paused = !view.paused;
+                        if config.no_title {
+                            let message = if view.paused { "paused" } else { "resumed" };
+                            show_toast(&mut stdout, &mut screen, message)?;
+                            toast_until = Some(Instant::now() + TOAST_DURATION);
+                        } else {
+                            last_frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                        }
+                    }
+                    WaitOutcome::Action(Action::ToggleDiff) if config.follow => {
+                        show_toast(&mut stdout, &mut screen, "diff highlighting is not available with --follow")?;
+                        toast_until = Some(Instant::now() + TOAST_DURATION);
+                    }
+                    WaitOutcome::Action(Action::ToggleDiff) => {
+                        view.differences = match view.differences {
+                            Some(_) => None,
+                            None => Some(config.differences.unwrap_or(DifferencesMode::Changes)),
+                        };
+                        // Highlights go at once; new ones come with the next run.
+                        if view.differences.is_none() {
+                            for pane in &mut panes {
+                                pane.grid = pane.raw.clone();
+                            }
+                        }
+                        last_frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                        let message = match view.differences {
+                            Some(_) => "highlighting changes",
+                            None => "not highlighting changes",
+                        };
+                        show_toast(&mut stdout, &mut screen, message)?;
+                        toast_until = Some(Instant::now() + TOAST_DURATION);
+                    }
+                    WaitOutcome::Action(Action::Scroll(scroll)) => {
+                        panes[view.focus].scroll(scroll);
+                        last_frame =
+                            redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                    }
+                    WaitOutcome::Action(Action::FocusNext) => {
+                        if panes.len() > 1 {
+                            view.focus = (view.focus + 1) % panes.len();
+                            last_frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                        }
+                    }
+                    WaitOutcome::Action(Action::Zoom) => {
+                        if panes.len() > 1 {
+                            view.zoomed = !view.zoomed;
+                            last_frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                        }
+                    }
+                    WaitOutcome::Action(action @ (Action::Search | Action::Filter)) => {
+                        view.prompt = Some(Prompt::new(match action {
+                            Action::Search => PromptKind::Search,
+                            _ => PromptKind::Filter,
+                        }));
+                        last_frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                    }
+                    WaitOutcome::Action(Action::Help) => {
+                        view.help = true;
+                        last_frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                    }
+                    WaitOutcome::Key(_) if view.help => {
+                        // Closing the help never reruns, whatever the key.
+                        view.help = false;
+                        last_frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                    }
+                    WaitOutcome::Key(key) => {
+                        let Some(prompt) = view.prompt.as_mut() else {
+                            continue;
+                        };
+                        match edit_prompt(&mut prompt.text, key) {
+                            PromptKey::Edited => {}
+                            PromptKey::Ignored => continue,
+                            PromptKey::Cancel => view.prompt = None,
+                            PromptKey::Submit => {
+                                let Some(Prompt { kind, text }) = view.prompt.take() else {
+                                    continue;
+                                };
+                                if kind == PromptKind::Filter {
+                                    // An empty filter shows everything again.
+                                    match filter_from(&config, &text) {
+                                        Ok(filter) => {
+                                            view.filter = filter;
+                                            if config.filter_checks == FilterChecks::Filtered {
+                                                for pane in &mut panes {
+                                                    pane.rebase_checks(view.filter.as_ref());
+                                                }
+                                            }
+                                        }
+                                        Err(err) => {
+                                            last_frame = redraw(
+                                                &mut stdout,
+                                                &mut screen,
+                                                &config,
+                                                &mut panes,
+                                                &view,
+                                            )?;
+                                            show_toast(&mut stdout, &mut screen, &err)?;
+                                            toast_until = Some(Instant::now() + TOAST_DURATION);
+                                            continue;
+                                        }
+                                    }
+                                    last_frame =
+                                        redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                                    continue;
+                                }
+                                view.search = Search::new(&text);
+                                if view.search.is_some() && !jump_to_match(&mut view, &mut panes, 0) {
+                                    last_frame =
+                                        redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                                    let message = not_found(&view);
+                                    show_toast(&mut stdout, &mut screen, &message)?;
+                                    toast_until = Some(Instant::now() + TOAST_DURATION);
+                                    continue;
+                                }
+                            }
+                        }
+                        last_frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                    }
+                    WaitOutcome::Action(action @ (Action::NextMatch | Action::PrevMatch)) => {
+                        if view.search.is_none() {
+                            continue;
+                        }
+                        let step = if action == Action::NextMatch { 1 } else { -1 };
+                        if jump_to_match(&mut view, &mut panes, step) {
+                            last_frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                        } else {
+                            let message = not_found(&view);
+                            show_toast(&mut stdout, &mut screen, &message)?;
+                            toast_until = Some(Instant::now() + TOAST_DURATION);
+                        }
+                    }
+                    WaitOutcome::Action(Action::ClearSearch) => {
+                        if view.search.take().is_some() {
+                            last_frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                        }
+                    }
+                    WaitOutcome::Resize => {
+                        // Rewrap what we have right away; the rerun, if
+                        // any, only touches what it changes.
+                        last_frame =
+                            redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                        if !config.no_rerun {
+                            break (0..panes.len()).collect();
+                        }
+                    }
+                    WaitOutcome::Timeout => {
+                        let now = Instant::now();
+                        let due: Vec<usize> = (0..panes.len())
+                            .filter(|&idx| !view.paused && now >= panes[idx].next_run)
+                            .collect();
+                        if !due.is_empty() {
+                            break due;
+                        }
+                        let toast_over = toast_until.is_some_and(|until| now >= until);
+                        if toast_over {
+                            toast_until = None;
+                        }
+                        if view.paused && toast_until.is_none() {
+                            // Keeps the clock in the header going.
+                            last_frame =
+                                redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+                        } else if toast_over {
+                            screen.draw(&mut stdout, &last_frame, terminal_size())?;
+                        }
+                    }
+                }
+            }
+        };
+        first = false;
+
+        hooks.reap();
+        let mut warning = None;
+        let mut reports = Vec::new();
+        for &idx in &due {
+            let pane = &mut panes[idx];
+            pane.run_started = Instant::now();
+            pane.run_number += 1;
+            let mut command = build_command(&pane.command, &exec_options)?;
+            let period = pane.schedule.period(&pane.pacer);
+            set_run_env(&mut command, pane.run_number, period, pane.prev_exit);
+            let exec_output = match (&piped_stdin, config.stdin.as_deref()) {
+                (Some(input), _) => run_command_with_input(command, input)?,
+                (None, Some(path)) => {
+                    let input = fs::read(path).map_err(|err| {
+                        ExecError::StdinFailed(format!("{}: {err}", path.display()))
+                    })?;
+                    run_command_with_input(command, &input)?
+                }
+                (None, None) => run_command(command)?,
+            };
+            pane.prev_exit = exec_output.status.code();
+            let success = exec_output.status.success();
+
+            let mut grid = Grid::parse_with_tab_width(
+                &String::from_utf8_lossy(&exec_output.combined()),
+                config.tab_width,
+            );
+            if matches!(config.color, ColorMode::Auto | ColorMode::Never) {
+                grid.clear_styles();
+            }
+
+            let diff_result = pane.diff_state.apply(&grid, view.differences);
+            // With a filter, the checks can look at only the lines it keeps,
+            // as if the output were piped to grep.
+            let (plain, changed) = match (&view.filter, config.filter_checks) {
+                (Some(filter), FilterChecks::Filtered) => {
+                    let kept = filter.apply(&grid).text();
+                    let changed = pane.checked.as_deref().is_some_and(|prev| prev != kept);
+                    pane.checked = Some(kept.clone());
+                    (kept, changed)
+                }
+                _ => {
+                    pane.checked = None;
+                    (grid.text(), diff_result.changed)
+                }
+            };
+            pane.pacer.record(success, changed || pane.run_number == 1);
+            pane.grid = diff_result.grid;
+            pane.raw = grid;
+            if config.follow {
+                pane.log.push(&pane.grid, Local::now());
+            }
+            if !hooks.is_empty() {
+                let context = HookContext {
+                    command: &pane.command,
+                    exit_code: exec_output.status.code(),
+                    old_output: &pane.output,
+                    new_output: &plain,
+                };
+                for event in HookEvent::for_run(changed, pane.last_success, success) {
+                    if let Err(err) = hooks.fire(event, &context) {
+                        warning = Some(format!("--on-{} hook failed: {err}", event.name()));
+                    }
+                }
+                pane.output = plain.clone();
+            }
+            pane.last_success = Some(success);
+            reports.push(RunReport {
+                pane: idx,
+                success,
+                code: exec_output.status.code(),
+                changed,
+                plain,
+            });
         }
-        stdout.flush()?;
 
-        if pending_screenshot {
-            if let Some(dir) = config.shotsdir.as_deref() {
-                save_screenshot(dir, &frame)?;
+        let frame = redraw(&mut stdout, &mut screen, &config, &mut panes, &view)?;
+
+        let mut fired = false;
+        for report in &reports {
+            let event = RunEvent {
+                changed: report.changed,
+                success: report.success,
+                output: &report.plain,
+            };
+            fired |= any_fires(&config.shot_on, &event);
+            if any_fires(&config.beep_on, &event)
+                && last_bell.is_none_or(|at| at.elapsed() >= config.bell_gap)
+            {
+                stdout.write_all(b"\x07")?;
+                stdout.flush()?;
+                last_bell = Some(Instant::now());
+            }
+            if let Err(err) = notifier.notify(&mut stdout, report.pane, &panes[report.pane].command, &event) {
+                warning = Some(format!("notification failed: {err}"));
             }
-            pending_screenshot = false;
+        }
+        if let Some(dir) = config.shotsdir.as_deref()
+            && fired
+            && let Err(err) = save_frame(dir, config.shots_keep, &frame)
+        {
+            warning = Some(format!("screenshot failed: {err}"));
+        }
+        toast_until = None;
+        if let Some(message) = warning {
+            show_toast(&mut stdout, &mut screen, &message)?;
+            toast_until = Some(Instant::now() + TOAST_DURATION);
         }
 
-        if config.errexit && !exec_output.status.success() {
+        if config.errexit
+            && let Some(report) = reports.iter().find(|report| !report.success)
+        {
             wait_for_keypress()?;
-            return Ok(exec_output.status.code().unwrap_or(1));
+            return Ok(report.code.unwrap_or(1));
         }
 
         if config.chgexit || config.equexit.is_some() {
-            let visible = visible_output(&frame, header_lines);
-            if let Some(prev) = last_visible.as_deref() {
-                if prev == visible {
-                    unchanged_cycles = unchanged_cycles.saturating_add(1);
-                } else {
-                    unchanged_cycles = 0;
-                }
+            // The output itself, not what scrolling or zoom leave on screen.
+            for report in &reports {
+                let pane = &mut panes[report.pane];
+                if let Some(prev) = pane.last_checked.as_deref() {
+                    if prev == report.plain {
+                        pane.unchanged_cycles = pane.unchanged_cycles.saturating_add(1);
+                    } else {
+                        pane.unchanged_cycles = 0;
+                    }
 
-                if config.chgexit && prev != visible {
-                    return Ok(0);
-                }
-                if let Some(limit) = config.equexit {
-                    if unchanged_cycles >= limit {
+                    if config.chgexit && prev != report.plain {
+                        hooks.finish(HOOK_EXIT_WAIT);
                         return Ok(0);
                     }
                 }
+                pane.last_checked = Some(report.plain.clone());
+            }
+            if let Some(limit) = config.equexit
+                && panes.iter().all(|pane| pane.unchanged_cycles >= limit)
+            {
+                hooks.finish(HOOK_EXIT_WAIT);
+                return Ok(0);
             }
-            last_visible = Some(visible);
         }
 
-        next_run = if config.precise {
-            run_started + config.interval
-        } else {
-            Instant::now() + config.interval
-        };
+        last_frame = frame;
+
+        let finished = Instant::now();
+        for &idx in &due {
+            let pane = &mut panes[idx];
+            pane.run_finished = finished;
+            pane.next_run = pane
+                .schedule
+                .next_run(pane.run_started, pane.run_finished, &mut pane.pacer);
+        }
     }
 }
 
-fn visible_output(frame: &[String], header_lines: usize) -> String {
+/// Lays the panes out on screen, with the prompt over the bottom row while
+/// it is open and the help over everything.
+fn build_frame(config: &Config, panes: &mut [Pane], view: &View, size: TerminalSize) -> Vec<Line> {
+    // Matches are highlighted, and lines filtered, as the text is typed.
+    // A filter that is not a valid regex yet leaves the last one in place.
+    let mut search = view.search.clone();
+    let mut filter = view.filter.clone();
+    match &view.prompt {
+        Some(Prompt {
+            kind: PromptKind::Search,
+            text,
+        }) => search = Search::new(text),
+        Some(Prompt {
+            kind: PromptKind::Filter,
+            text,
+        }) => filter = filter_from(config, text).unwrap_or(filter),
+        None => {}
+    }
+    let mut frame = layout_panes(config, panes, view, search.as_ref(), filter.as_ref(), size);
+    if let Some(Prompt { kind, text }) = &view.prompt {
+        frame.resize(size.rows as usize, Line::default());
+        let prefix = match kind {
+            PromptKind::Search => '/',
+            PromptKind::Filter => '&',
+        };
+        let mut prompt = Line::plain(&format!("{prefix}{text}"));
+        prompt.extend(Line::styled(
+            " ",
+            Style {
+                reverse: true,
+                ..Style::default()
+            },
+        ));
+        frame[size.rows as usize - 1] = prompt;
+    }
+    if view.help {
+        let schedule = pane_label(&panes[view.focus], view, None);
+        overlay(&mut frame, &help_lines(config, &schedule, view.differences), size);
+    }
     frame
-        .iter()
-        .skip(header_lines)
-        .cloned()
-        .collect::<Vec<_>>()
-        .join("\n")
 }
 
-fn write_frame(stdout: &mut impl Write, frame: &[String]) -> Result<(), AppError> {
-    let last = frame.len().saturating_sub(1);
-    for (idx, line) in frame.iter().enumerate() {
-        stdout.write_all(line.as_bytes())?;
-        if idx != last {
-            stdout.write_all(b"\n")?;
+/// A single pane, or the zoomed one, gets the classic layout: header, blank
+/// line, output.
+fn layout_panes(
+    config: &Config,
+    panes: &mut [Pane],
+    view: &View,
+    search: Option<&Search>,
+    filter: Option<&Filter>,
+    size: TerminalSize,
+) -> Vec<Line> {
+    let now = Local::now();
+
+    if panes.len() == 1 || view.zoomed {
+        let pane = &mut panes[if view.zoomed { view.focus } else { 0 }];
+        let mut frame = Vec::new();
+        if !config.no_title {
+            let label = pane_label(pane, view, filter);
+            let header = header_line(&pane.command, &label, now, size.columns);
+            frame.push(Line::plain(&header));
+            frame.push(Line::default());
         }
+        let lines = pane.rows(config, filter, search, size.columns as usize);
+        let rows = (size.rows as usize).saturating_sub(frame.len());
+        frame.extend(pane.visible_lines(lines, rows, config.follow));
+        return frame;
+    }
+
+    let areas = pane_areas(panes.len(), config.split, size.columns, size.rows);
+    let blocks: Vec<Vec<Line>> = panes
+        .iter_mut()
+        .zip(&areas)
+        .enumerate()
+        .map(|(idx, (pane, area))| {
+            let mut block = Vec::new();
+            if !config.no_title {
+                let label = pane_label(pane, view, filter);
+                let header = pane_header(&pane.command, &label, now, area.columns);
+                let style = Style {
+                    reverse: idx == view.focus,
+                    ..Style::default()
+                };
+                block.push(Line::styled(&header, style));
+            }
+            let rows = (area.rows as usize).saturating_sub(block.len());
+            let lines = pane.rows(config, filter, search, area.columns as usize);
+            block.extend(pane.visible_lines(lines, rows, false));
+            block.resize(area.rows as usize, Line::default());
+            block
+        })
+        .collect();
+
+    match config.split {
+        SplitDirection::Horizontal => {
+            let mut frame = Vec::new();
+            for (idx, block) in blocks.into_iter().enumerate() {
+                if idx > 0 {
+                    frame.push(divider_line(size.columns));
+                }
+                frame.extend(block);
+            }
+            frame
+        }
+        SplitDirection::Vertical => {
+            let widths: Vec<u16> = areas.iter().map(|area| area.columns).collect();
+            join_columns(&blocks, &widths)
+        }
+    }
+}
+
+/// Draws the panes' latest output, repainting only what changed. Also used
+/// to redraw without rerunning anything.
+fn redraw(
+    stdout: &mut impl Write,
+    screen: &mut Screen,
+    config: &Config,
+    panes: &mut [Pane],
+    view: &View,
+) -> Result<Vec<Line>, AppError> {
+    let size = terminal_size();
+    let frame = build_frame(config, panes, view, size);
+    if config.follow {
+        // The log only grows, so moving down it is a scroll below the
+        // header.
+        let top = if config.no_title { 0 } else { 2 };
+        screen.scroll(top, panes[0].shift);
+    }
+    screen.draw(stdout, &frame, size)?;
+    Ok(frame)
+}
+
+/// Moves the focused pane to a match of the search: with `step` 0 the first
+/// one from the top of the view on, otherwise the next or previous one.
+/// Returns false when nothing matches.
+fn jump_to_match(view: &mut View, panes: &mut [Pane], step: isize) -> bool {
+    let Some(search) = view.search.as_mut() else {
+        return false;
+    };
+    let pane = &mut panes[view.focus];
+    let count = pane.matches.len();
+    if count == 0 {
+        return false;
     }
+    search.current = match step {
+        0 => pane
+            .matches
+            .iter()
+            .position(|&row| row >= pane.scroll)
+            .unwrap_or(0),
+        _ => (search.current as isize + step).rem_euclid(count as isize) as usize,
+    };
+    pane.scroll_to(pane.matches[search.current]);
+    true
+}
+
+/// The filter typed after `&`, using the context and inversion from the
+/// config. `None` for an empty one.
+fn filter_from(config: &Config, text: &str) -> Result<Option<Filter>, String> {
+    if text.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Filter {
+        pattern: Pattern::new(text)?,
+        context: config.filter_context,
+        invert: config.filter_invert,
+    }))
+}
+
+/// The pane's schedule for its header, noting a pause and an active filter.
+fn pane_label(pane: &Pane, view: &View, filter: Option<&Filter>) -> String {
+    let mut label = pane.schedule.label(&pane.pacer);
+    if view.paused {
+        label.push_str(", paused");
+    }
+    match filter {
+        Some(filter) if filter.invert => format!("{label}, without '{}'", filter.pattern.as_str()),
+        Some(filter) => format!("{label}, filter '{}'", filter.pattern.as_str()),
+        None => label,
+    }
+}
+
+fn not_found(view: &View) -> String {
+    let query = view.search.as_ref().map_or("", Search::query);
+    format!("pattern not found: {query}")
+}
+
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+/// How often the header clock is redrawn while paused.
+const PAUSED_POLL: Duration = Duration::from_secs(1);
+
+fn save_frame(dir: &Path, keep: Option<usize>, frame: &[Line]) -> io::Result<PathBuf> {
+    let path = save_screenshot(dir, frame)?;
+    if let Some(keep) = keep {
+        prune_screenshots(dir, keep)?;
+    }
+    Ok(path)
+}
+
+fn show_toast(stdout: &mut impl Write, screen: &mut Screen, message: &str) -> Result<(), AppError> {
+    let size = terminal_size();
+    let line = Grid::parse(&toast_line(message, size.columns)).lines.remove(0);
+    screen.draw_row(stdout, size.rows as usize - 1, line, size)?;
     Ok(())
 }
+
@@ -2,15 +2,30 @@ use std::path::PathBuf;
 
 use clap::{ArgAction, Parser};
 
+use crate::condition::{parse_condition, Condition};
 use crate::config::DifferencesMode;
+use crate::cron::{parse_cron, CronSchedule};
+use crate::filter::{parse_filter_checks, FilterChecks};
+use crate::notify::{parse_notify_rule, NotifyRule};
+use crate::pane::{parse_split, SplitDirection};
+use crate::pattern::Pattern;
 
 #[derive(Parser, Debug, Clone, PartialEq, Eq)]
 #[command(name = "watch", version, about = "Execute a program periodically, showing output fullscreen")]
 #[command(trailing_var_arg = true, allow_hyphen_values = true)]
 pub struct Cli {
-    #[arg(short = 'b', long = "beep", action = ArgAction::SetTrue)]
+    #[arg(short = 'b', long = "beep", action = ArgAction::SetTrue, overrides_with = "no_beep")]
     pub beep: bool,
 
+    #[arg(long = "no-beep", action = ArgAction::SetTrue, overrides_with = "beep", hide = true)]
+    pub no_beep: bool,
+
+    #[arg(long = "beep-on", value_name = "when", value_parser = parse_condition, action = ArgAction::Append)]
+    pub beep_on: Vec<Condition>,
+
+    #[arg(long = "bell-gap", value_name = "interval")]
+    pub bell_gap: Option<String>,
+
     #[arg(short = 'c', long = "color", action = ArgAction::SetTrue)]
     pub color: bool,
 
@@ -23,40 +38,178 @@ pub struct Cli {
     #[arg(long = "differences", require_equals = true, value_name = "permanent", value_parser = parse_differences)]
     pub differences: Option<DifferencesMode>,
 
-    #[arg(short = 'e', long = "errexit", action = ArgAction::SetTrue)]
+    #[arg(short = 'e', long = "errexit", action = ArgAction::SetTrue, overrides_with = "no_errexit")]
     pub errexit: bool,
 
-    #[arg(short = 'f', long = "follow", action = ArgAction::SetTrue)]
+    #[arg(long = "no-errexit", action = ArgAction::SetTrue, overrides_with = "errexit", hide = true)]
+    pub no_errexit: bool,
+
+    #[arg(short = 'f', long = "follow", action = ArgAction::SetTrue, overrides_with = "no_follow")]
     pub follow: bool,
 
-    #[arg(short = 'g', long = "chgexit", action = ArgAction::SetTrue)]
+    #[arg(long = "no-follow", action = ArgAction::SetTrue, overrides_with = "follow", hide = true)]
+    pub no_follow: bool,
+
+    #[arg(long = "separator", action = ArgAction::SetTrue, overrides_with = "no_separator")]
+    pub separator: bool,
+
+    #[arg(long = "no-separator", action = ArgAction::SetTrue, overrides_with = "separator", hide = true)]
+    pub no_separator: bool,
+
+    #[arg(long = "dedup", action = ArgAction::SetTrue, overrides_with = "no_dedup")]
+    pub dedup: bool,
+
+    #[arg(long = "no-dedup", action = ArgAction::SetTrue, overrides_with = "dedup", hide = true)]
+    pub no_dedup: bool,
+
+    #[arg(short = 'g', long = "chgexit", action = ArgAction::SetTrue, overrides_with = "no_chgexit")]
     pub chgexit: bool,
 
+    #[arg(long = "no-chgexit", action = ArgAction::SetTrue, overrides_with = "chgexit", hide = true)]
+    pub no_chgexit: bool,
+
     #[arg(short = 'n', long = "interval", value_name = "secs")]
     pub interval: Option<String>,
 
-    #[arg(short = 'p', long = "precise", action = ArgAction::SetTrue)]
+    #[arg(short = 'p', long = "precise", action = ArgAction::SetTrue, overrides_with = "no_precise")]
     pub precise: bool,
 
+    #[arg(long = "no-precise", action = ArgAction::SetTrue, overrides_with = "precise", hide = true)]
+    pub no_precise: bool,
+
+    #[arg(long = "align", action = ArgAction::SetTrue, overrides_with = "no_align")]
+    pub align: bool,
+
+    #[arg(long = "no-align", action = ArgAction::SetTrue, overrides_with = "align", hide = true)]
+    pub no_align: bool,
+
+    #[arg(long = "cron", value_name = "expr", value_parser = parse_cron_arg)]
+    pub cron: Option<CronSchedule>,
+
+    #[arg(long = "backoff", value_name = "max", num_args = 0..=1, require_equals = true, default_missing_value = "5m")]
+    pub backoff: Option<String>,
+
+    #[arg(long = "adaptive", value_name = "max", num_args = 0..=1, require_equals = true, default_missing_value = "1m")]
+    pub adaptive: Option<String>,
+
     #[arg(short = 'q', long = "equexit", value_name = "cycles")]
     pub equexit: Option<u32>,
 
-    #[arg(short = 'r', long = "no-rerun", action = ArgAction::SetTrue)]
+    #[arg(short = 'r', long = "no-rerun", action = ArgAction::SetTrue, overrides_with = "rerun")]
     pub no_rerun: bool,
 
+    #[arg(long = "rerun", action = ArgAction::SetTrue, overrides_with = "no_rerun", hide = true)]
+    pub rerun: bool,
+
     #[arg(short = 's', long = "shotsdir", value_name = "dir")]
     pub shotsdir: Option<PathBuf>,
 
-    #[arg(short = 't', long = "no-title", action = ArgAction::SetTrue)]
+    #[arg(long = "shot-on", value_name = "when", value_parser = parse_condition, action = ArgAction::Append)]
+    pub shot_on: Vec<Condition>,
+
+    #[arg(long = "shots-keep", value_name = "count")]
+    pub shots_keep: Option<usize>,
+
+    #[arg(short = 't', long = "no-title", action = ArgAction::SetTrue, overrides_with = "title")]
     pub no_title: bool,
 
-    #[arg(short = 'w', long = "no-wrap", alias = "no-linewrap", action = ArgAction::SetTrue)]
+    #[arg(long = "title", action = ArgAction::SetTrue, overrides_with = "no_title", hide = true)]
+    pub title: bool,
+
+    #[arg(short = 'w', long = "no-wrap", alias = "no-linewrap", action = ArgAction::SetTrue, overrides_with = "wrap")]
     pub no_wrap: bool,
 
-    #[arg(short = 'x', long = "exec", action = ArgAction::SetTrue)]
+    #[arg(long = "wrap", action = ArgAction::SetTrue, overrides_with = "no_wrap", hide = true)]
+    pub wrap: bool,
+
+    #[arg(long = "tab-width", value_name = "cols")]
+    pub tab_width: Option<usize>,
+
+    #[arg(long = "watch-path", value_name = "path", action = ArgAction::Append)]
+    pub watch_path: Vec<PathBuf>,
+
+    #[arg(long = "watch-exclude", value_name = "glob", action = ArgAction::Append)]
+    pub watch_exclude: Vec<String>,
+
+    #[arg(long = "watch-debounce", value_name = "interval")]
+    pub watch_debounce: Option<String>,
+
+    #[arg(long = "watch-fallback", action = ArgAction::SetTrue, overrides_with = "no_watch_fallback")]
+    pub watch_fallback: bool,
+
+    #[arg(long = "no-watch-fallback", action = ArgAction::SetTrue, overrides_with = "watch_fallback", hide = true)]
+    pub no_watch_fallback: bool,
+
+    #[arg(short = 'x', long = "exec", action = ArgAction::SetTrue, overrides_with = "no_exec")]
     pub exec: bool,
 
-    #[arg(value_name = "command", required = true, num_args(1..))]
+    #[arg(long = "no-exec", action = ArgAction::SetTrue, overrides_with = "exec", hide = true)]
+    pub no_exec: bool,
+
+    #[arg(long = "shell", value_name = "program")]
+    pub shell: Option<String>,
+
+    #[arg(long = "shell-arg", value_name = "arg", allow_hyphen_values = true, action = ArgAction::Append)]
+    pub shell_arg: Vec<String>,
+
+    #[arg(long = "chdir", value_name = "dir")]
+    pub chdir: Option<PathBuf>,
+
+    #[arg(long = "env", value_name = "KEY=VAL", value_parser = parse_env_pair, action = ArgAction::Append)]
+    pub env: Vec<(String, String)>,
+
+    #[arg(long = "env-clear", action = ArgAction::SetTrue, overrides_with = "no_env_clear")]
+    pub env_clear: bool,
+
+    #[arg(long = "no-env-clear", action = ArgAction::SetTrue, overrides_with = "env_clear", hide = true)]
+    pub no_env_clear: bool,
+
+    #[arg(long = "stdin", value_name = "file|-")]
+    pub stdin: Option<PathBuf>,
+
+    #[arg(long = "on-change", value_name = "cmd")]
+    pub on_change: Option<String>,
+
+    #[arg(long = "on-fail", value_name = "cmd")]
+    pub on_fail: Option<String>,
+
+    #[arg(long = "on-recover", value_name = "cmd")]
+    pub on_recover: Option<String>,
+
+    #[arg(long = "hook-gap", value_name = "interval")]
+    pub hook_gap: Option<String>,
+
+    #[arg(long = "notify", value_name = "backend:when", value_parser = parse_notify_rule, action = ArgAction::Append)]
+    pub notify: Vec<NotifyRule>,
+
+    #[arg(long = "notify-command", value_name = "program")]
+    pub notify_command: Option<String>,
+
+    #[arg(long = "split", value_name = "horizontal|vertical", value_parser = parse_split)]
+    pub split: Option<SplitDirection>,
+
+    #[arg(long = "filter", value_name = "regex", value_parser = Pattern::new)]
+    pub filter: Option<Pattern>,
+
+    #[arg(long = "filter-context", value_name = "lines")]
+    pub filter_context: Option<usize>,
+
+    #[arg(long = "filter-invert", action = ArgAction::SetTrue, overrides_with = "no_filter_invert")]
+    pub filter_invert: bool,
+
+    #[arg(long = "no-filter-invert", action = ArgAction::SetTrue, overrides_with = "filter_invert", hide = true)]
+    pub no_filter_invert: bool,
+
+    #[arg(long = "filter-checks", value_name = "filtered|full", value_parser = parse_filter_checks)]
+    pub filter_checks: Option<FilterChecks>,
+
+    #[arg(long = "config", value_name = "file")]
+    pub config: Option<PathBuf>,
+
+    #[arg(short = 'P', long = "profile", value_name = "name")]
+    pub profile: Option<String>,
+
+    #[arg(value_name = "command", num_args(1..))]
     pub command: Vec<String>,
 }
 
@@ -71,6 +224,18 @@ fn parse_differences(value: &str) -> Result<DifferencesMode, String> {
     }
 }
 
+/// Splits `KEY=VAL` at the first `=`; the value may be empty.
+pub fn parse_env_pair(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, val)) if !key.is_empty() => Ok((key.to_string(), val.to_string())),
+        _ => Err(format!("expected KEY=VAL, got '{value}'")),
+    }
+}
+
+fn parse_cron_arg(value: &str) -> Result<CronSchedule, String> {
+    parse_cron(value).map_err(|err| err.to_string())
+}
+
 impl Cli {
     pub fn parse_args() -> Result<Self, clap::Error> {
         Self::parse_from_iter(std::env::args_os())
@@ -111,11 +276,11 @@ where
             continue;
         }
 
-        if let Some(value) = raw_str.strip_prefix("-d") {
-            if !value.is_empty() {
-                out.push(format!("--differences={value}").into());
-                continue;
-            }
+        if let Some(value) = raw_str.strip_prefix("-d")
+            && !value.is_empty()
+        {
+            out.push(format!("--differences={value}").into());
+            continue;
         }
 
         out.push(raw);
@@ -146,4 +311,63 @@ mod tests {
         let cli = Cli::parse_from_iter(["watch", "-d1", "echo", "hi"]).unwrap();
         assert_eq!(cli.differences, Some(DifferencesMode::Permanent));
     }
+
+    #[test]
+    fn parses_backoff_with_and_without_cap() {
+        let cli = Cli::parse_from_iter(["watch", "--backoff", "echo", "hi"]).unwrap();
+        assert_eq!(cli.backoff.as_deref(), Some("5m"));
+        assert_eq!(cli.command, vec!["echo", "hi"]);
+        let cli = Cli::parse_from_iter(["watch", "--backoff=30s", "echo"]).unwrap();
+        assert_eq!(cli.backoff.as_deref(), Some("30s"));
+    }
+
+    #[test]
+    fn parses_hyphenated_shell_args() {
+        let cli = Cli::parse_from_iter([
+            "watch",
+            "--shell",
+            "pwsh",
+            "--shell-arg",
+            "-NoProfile",
+            "--shell-arg",
+            "-Command",
+            "Get-Date",
+        ])
+        .unwrap();
+        assert_eq!(cli.shell.as_deref(), Some("pwsh"));
+        assert_eq!(cli.shell_arg, vec!["-NoProfile", "-Command"]);
+        assert_eq!(cli.command, vec!["Get-Date"]);
+    }
+
+    #[test]
+    fn parses_repeated_env_pairs() {
+        let cli =
+            Cli::parse_from_iter(["watch", "--env", "A=1", "--env", "B=x=y", "--env", "C=", "env"])
+                .unwrap();
+        assert_eq!(
+            cli.env,
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "x=y".to_string()),
+                ("C".to_string(), String::new()),
+            ]
+        );
+        assert!(Cli::parse_from_iter(["watch", "--env", "NOVALUE", "env"]).is_err());
+        assert!(Cli::parse_from_iter(["watch", "--env", "=1", "env"]).is_err());
+    }
+
+    #[test]
+    fn parses_repeated_shot_on() {
+        let cli = Cli::parse_from_iter([
+            "watch",
+            "--shot-on=fail",
+            "--shot-on",
+            "match:ERR",
+            "echo",
+            "hi",
+        ])
+        .unwrap();
+        assert_eq!(cli.shot_on.len(), 2);
+        assert_eq!(cli.shot_on[0], Condition::Fail);
+    }
 }
@@ -0,0 +1,84 @@
+use crate::pattern::Pattern;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    Change,
+    Fail,
+    Success,
+    Match(Pattern),
+    Always,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunEvent<'a> {
+    pub changed: bool,
+    pub success: bool,
+    pub output: &'a str,
+}
+
+impl Condition {
+    pub fn fires(&self, event: &RunEvent<'_>) -> bool {
+        match self {
+            Condition::Change => event.changed,
+            Condition::Fail => !event.success,
+            Condition::Success => event.success,
+            Condition::Match(pattern) => pattern.is_match(event.output),
+            Condition::Always => true,
+        }
+    }
+}
+
+pub fn parse_condition(value: &str) -> Result<Condition, String> {
+    let trimmed = value.trim();
+    if let Some(source) = trimmed.strip_prefix("match:") {
+        return Pattern::new(source).map(Condition::Match);
+    }
+
+    match trimmed.to_ascii_lowercase().as_str() {
+        "change" => Ok(Condition::Change),
+        "fail" => Ok(Condition::Fail),
+        "success" => Ok(Condition::Success),
+        "always" => Ok(Condition::Always),
+        _ => Err("expected 'change', 'fail', 'success', 'match:<regex>' or 'always'".to_string()),
+    }
+}
+
+pub fn any_fires(conditions: &[Condition], event: &RunEvent<'_>) -> bool {
+    conditions.iter().any(|condition| condition.fires(event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(changed: bool, success: bool, output: &str) -> RunEvent<'_> {
+        RunEvent {
+            changed,
+            success,
+            output,
+        }
+    }
+
+    #[test]
+    fn parses_known_conditions() {
+        assert_eq!(parse_condition("change").unwrap(), Condition::Change);
+        assert_eq!(parse_condition("FAIL").unwrap(), Condition::Fail);
+        assert_eq!(parse_condition("always").unwrap(), Condition::Always);
+        assert_eq!(parse_condition("success").unwrap(), Condition::Success);
+        assert!(matches!(
+            parse_condition("match:err(or)?").unwrap(),
+            Condition::Match(_)
+        ));
+        assert!(parse_condition("sometimes").is_err());
+    }
+
+    #[test]
+    fn conditions_fire_on_matching_events() {
+        assert!(Condition::Change.fires(&event(true, true, "")));
+        assert!(!Condition::Change.fires(&event(false, true, "")));
+        assert!(Condition::Fail.fires(&event(false, false, "")));
+        let matcher = parse_condition("match:ERROR").unwrap();
+        assert!(matcher.fires(&event(false, true, "an ERROR here")));
+        assert!(!matcher.fires(&event(false, true, "all good")));
+    }
+}
@@ -2,7 +2,19 @@ use std::path::PathBuf;
 use std::time::Duration;
 
 use crate::cli::Cli;
-use crate::interval::{default_interval, parse_interval};
+use crate::condition::Condition;
+use crate::cron::CronSchedule;
+use crate::filter::{Filter, FilterChecks};
+use crate::grid::{DEFAULT_TAB_WIDTH, MAX_TAB_WIDTH};
+use crate::hooks::{HookEvent, DEFAULT_HOOK_GAP};
+use crate::interval::default_interval;
+use crate::keymap::Keymap;
+use crate::notify::{NotifyBackend, NotifyRule, DEFAULT_NOTIFY_COMMAND};
+use crate::pane::{split_panes, PaneCommand, SplitDirection};
+use crate::settings::{default_config_path, ConfigFile, Settings};
+use crate::watcher::DEFAULT_DEBOUNCE;
+
+const DEFAULT_BELL_GAP: Duration = Duration::from_secs(5);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DifferencesMode {
@@ -21,68 +33,280 @@ pub enum ColorMode {
 pub struct Config {
     pub interval: Duration,
     pub precise: bool,
+    pub align: bool,
+    pub cron: Option<CronSchedule>,
+    pub backoff: Option<Duration>,
+    pub adaptive: Option<Duration>,
     pub no_title: bool,
     pub no_wrap: bool,
+    /// Columns between tab stops when expanding tabs in the output.
+    pub tab_width: usize,
     pub differences: Option<DifferencesMode>,
-    pub beep: bool,
+    /// Ring the bell when any of these hold for a run; `--beep` adds `fail`.
+    pub beep_on: Vec<Condition>,
+    /// Keeps a flapping command from ringing the bell on every run.
+    pub bell_gap: Duration,
     pub errexit: bool,
     pub chgexit: bool,
     pub equexit: Option<u32>,
     pub follow: bool,
+    /// Put a timestamped rule above each run's output in `--follow` mode.
+    pub separator: bool,
+    /// Fold runs whose output repeats the previous one in `--follow` mode.
+    pub dedup: bool,
     pub no_rerun: bool,
     pub exec: bool,
     pub color: ColorMode,
     pub shotsdir: Option<PathBuf>,
-    pub command: Vec<String>,
+    pub shot_on: Vec<Condition>,
+    pub shots_keep: Option<usize>,
+    pub watch_paths: Vec<PathBuf>,
+    pub watch_excludes: Vec<String>,
+    pub watch_debounce: Duration,
+    pub watch_fallback: bool,
+    pub shell: Option<String>,
+    pub shell_args: Vec<String>,
+    pub chdir: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    pub env_clear: bool,
+    /// File fed to the command's stdin on every run; `-` reads watch's own
+    /// stdin once at startup.
+    pub stdin: Option<PathBuf>,
+    pub split: SplitDirection,
+    /// Only show the lines matching this; can be changed while running.
+    pub filter: Option<Filter>,
+    /// Lines kept around matches, also for a filter entered while running.
+    pub filter_context: usize,
+    pub filter_invert: bool,
+    pub filter_checks: FilterChecks,
+    /// Commands run in the background when a run raises the event.
+    pub hooks: Vec<(HookEvent, String)>,
+    pub hook_gap: Duration,
+    pub notify: Vec<NotifyRule>,
+    pub notify_command: String,
+    /// The default key bindings with any from the `[keys]` table.
+    pub keymap: Keymap,
+    /// The commands to watch; more than one when separated by `:::`.
+    pub panes: Vec<PaneCommand>,
 }
 
 impl Config {
+    /// Builds the configuration from the config file, the selected profile,
+    /// `WATCH_*` environment variables and the command line, in increasing
+    /// order of precedence.
     pub fn from_cli(cli: Cli) -> Result<Self, String> {
-        let interval = if let Some(raw) = cli.interval {
-            parse_interval(&raw).map_err(|err| err.to_string())?
-        } else if let Ok(raw) = std::env::var("WATCH_INTERVAL") {
-            parse_interval(&raw).map_err(|err| err.to_string())?
-        } else {
-            default_interval()
-        };
+        Self::from_cli_with(cli, &|key| std::env::var(key).ok())
+    }
 
-        let color = match (cli.color, cli.no_color) {
-            (true, true) => {
-                return Err("options --color and --no-color are mutually exclusive".to_string())
-            }
-            (true, false) => ColorMode::Always,
-            (false, true) => ColorMode::Never,
-            (false, false) => ColorMode::Auto,
+    /// Like `from_cli`, with the environment read through `var`.
+    pub fn from_cli_with(cli: Cli, var: &dyn Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let env_var = |key: &str| var(key).filter(|value| !value.is_empty());
+        let config_path = cli.config.clone().or_else(|| env_var("WATCH_CONFIG").map(PathBuf::from));
+        let profile = cli.profile.clone().or_else(|| env_var("WATCH_PROFILE"));
+
+        let file = match config_path {
+            Some(path) => Some(ConfigFile::load(&path)?),
+            None => match default_config_path(var) {
+                Some(path) if path.is_file() => Some(ConfigFile::load(&path)?),
+                _ => None,
+            },
         };
 
-        let differences = if cli.differences.is_some() {
-            cli.differences
-        } else if cli.differences_flag {
-            Some(DifferencesMode::Changes)
-        } else {
-            None
+        let base = match (&file, profile.as_deref()) {
+            (Some(file), profile) => file.resolve(profile)?,
+            (None, Some(_)) => return Err("option --profile requires a config file".to_string()),
+            (None, None) => Settings::default(),
         };
 
-        if cli.follow && (differences.is_some() || cli.chgexit || cli.equexit.is_some()) {
+        let cli = Settings::from_cli(cli)?;
+        let env = Settings::from_env_under(var, &cli).map_err(|err| err.to_string())?;
+        let settings = base.merge(env).merge(cli);
+        Self::from_settings(settings)
+    }
+
+    pub fn from_settings(settings: Settings) -> Result<Self, String> {
+        let precise = settings.precise.unwrap_or(false);
+        let align = settings.align.unwrap_or(false);
+        if settings.cron.is_some() && (settings.interval.is_some() || precise || align) {
+            return Err(
+                "option --cron is not compatible with --interval, --precise or --align".to_string(),
+            );
+        }
+        if (settings.backoff.is_some() || settings.adaptive.is_some())
+            && (settings.cron.is_some() || align)
+        {
+            return Err(
+                "options --backoff and --adaptive are not compatible with --cron or --align"
+                    .to_string(),
+            );
+        }
+        if align && precise {
+            return Err("options --align and --precise are mutually exclusive".to_string());
+        }
+
+        let follow = settings.follow.unwrap_or(false);
+        let chgexit = settings.chgexit.unwrap_or(false);
+        if follow && (settings.differences.is_some() || chgexit || settings.equexit.is_some()) {
             return Err("option --follow is not compatible with output tracking options".to_string());
         }
+        let separator = settings.separator.unwrap_or(false);
+        let dedup = settings.dedup.unwrap_or(false);
+        if !follow && (separator || dedup) {
+            let option = if separator { "--separator" } else { "--dedup" };
+            return Err(format!("option {option} requires --follow"));
+        }
+
+        let shot_on = settings.shot_on.unwrap_or_default();
+        if !shot_on.is_empty() && settings.shotsdir.is_none() {
+            return Err("option --shot-on requires --shotsdir".to_string());
+        }
+        let filter_context = settings.filter_context.unwrap_or(0);
+        let filter_invert = settings.filter_invert.unwrap_or(false);
+
+        if let Some(width) = settings.tab_width
+            && !(1..=MAX_TAB_WIDTH).contains(&width)
+        {
+            return Err(format!("option --tab-width must be between 1 and {MAX_TAB_WIDTH}"));
+        }
+        if settings.shots_keep == Some(0) {
+            return Err("option --shots-keep must be at least 1".to_string());
+        }
+
+        let mut beep_on = settings.beep_on.unwrap_or_default();
+        if settings.beep.unwrap_or(false) && !beep_on.contains(&Condition::Fail) {
+            beep_on.push(Condition::Fail);
+        }
+        if beep_on.is_empty() && settings.bell_gap.is_some() {
+            return Err("option --bell-gap requires --beep or --beep-on".to_string());
+        }
+
+        let watch_paths = settings.watch_paths.unwrap_or_default();
+        let watch_excludes = settings.watch_excludes.unwrap_or_default();
+        let watch_fallback = settings.watch_fallback.unwrap_or(false);
+        if watch_paths.is_empty()
+            && (!watch_excludes.is_empty() || settings.watch_debounce.is_some() || watch_fallback)
+        {
+            return Err(
+                "options --watch-exclude, --watch-debounce and --watch-fallback require --watch-path"
+                    .to_string(),
+            );
+        }
+
+        let exec = settings.exec.unwrap_or(false);
+        let shell_args = settings.shell_args.unwrap_or_default();
+        if exec && (settings.shell.is_some() || !shell_args.is_empty()) {
+            return Err("options --shell and --shell-arg are not compatible with --exec".to_string());
+        }
+
+        let hooks: Vec<(HookEvent, String)> = [
+            (HookEvent::Change, settings.on_change),
+            (HookEvent::Fail, settings.on_fail),
+            (HookEvent::Recover, settings.on_recover),
+        ]
+        .into_iter()
+        .filter_map(|(event, hook)| hook.map(|hook| (event, hook)))
+        .collect();
+        if hooks.is_empty() && settings.hook_gap.is_some() {
+            return Err(
+                "option --hook-gap requires --on-change, --on-fail or --on-recover".to_string(),
+            );
+        }
+
+        let notify = settings.notify.unwrap_or_default();
+        if settings.notify_command.is_some()
+            && !notify
+                .iter()
+                .any(|rule| rule.backend == NotifyBackend::Desktop)
+        {
+            return Err("option --notify-command requires --notify desktop:<when>".to_string());
+        }
+
+        if let Some(dir) = &settings.chdir
+            && !dir.is_dir()
+        {
+            return Err(format!("option --chdir: not a directory: {}", dir.display()));
+        }
+
+        // `:::` only separates panes with --split, so that commands such as
+        // `parallel echo ::: a b` can still use it.
+        let panes = match settings.command {
+            Some(command) if !command.is_empty() && settings.split.is_some() => {
+                split_panes(&command)?
+            }
+            Some(command) if !command.is_empty() => vec![PaneCommand {
+                command,
+                interval: None,
+            }],
+            _ => return Err("no command given".to_string()),
+        };
+        if panes.len() > 1 && follow {
+            return Err("option --follow cannot be used with multiple commands".to_string());
+        }
+        let on_change_only = !watch_paths.is_empty() && !watch_fallback && !align;
+        if panes.iter().any(|pane| pane.interval.is_some())
+            && (settings.cron.is_some() || on_change_only)
+        {
+            return Err(
+                "per-command intervals are not compatible with --cron or --watch-path without --watch-fallback"
+                    .to_string(),
+            );
+        }
+
+        let keymap = Keymap::new(settings.keys.as_deref().unwrap_or_default())
+            .map_err(|err| format!("keys: {err}"))?;
 
         Ok(Self {
-            interval,
-            precise: cli.precise,
-            no_title: cli.no_title,
-            no_wrap: cli.no_wrap,
-            differences,
-            beep: cli.beep,
-            errexit: cli.errexit,
-            chgexit: cli.chgexit,
-            equexit: cli.equexit,
-            follow: cli.follow,
-            no_rerun: cli.no_rerun,
-            exec: cli.exec,
-            color,
-            shotsdir: cli.shotsdir,
-            command: cli.command,
+            interval: settings.interval.unwrap_or_else(default_interval),
+            precise,
+            align,
+            cron: settings.cron,
+            backoff: settings.backoff,
+            adaptive: settings.adaptive,
+            no_title: settings.no_title.unwrap_or(false),
+            no_wrap: settings.no_wrap.unwrap_or(false),
+            tab_width: settings.tab_width.unwrap_or(DEFAULT_TAB_WIDTH),
+            differences: settings.differences,
+            beep_on,
+            bell_gap: settings.bell_gap.unwrap_or(DEFAULT_BELL_GAP),
+            errexit: settings.errexit.unwrap_or(false),
+            chgexit,
+            equexit: settings.equexit,
+            follow,
+            separator,
+            dedup,
+            no_rerun: settings.no_rerun.unwrap_or(false),
+            exec,
+            color: settings.color.unwrap_or(ColorMode::Auto),
+            shotsdir: settings.shotsdir,
+            shot_on,
+            shots_keep: settings.shots_keep,
+            watch_paths,
+            watch_excludes,
+            watch_debounce: settings.watch_debounce.unwrap_or(DEFAULT_DEBOUNCE),
+            watch_fallback,
+            shell: settings.shell,
+            shell_args,
+            chdir: settings.chdir,
+            env: settings.env.unwrap_or_default(),
+            env_clear: settings.env_clear.unwrap_or(false),
+            stdin: settings.stdin,
+            split: settings.split.unwrap_or(SplitDirection::Horizontal),
+            filter: settings.filter.map(|pattern| Filter {
+                pattern,
+                context: filter_context,
+                invert: filter_invert,
+            }),
+            filter_context,
+            filter_invert,
+            filter_checks: settings.filter_checks.unwrap_or(FilterChecks::Filtered),
+            hooks,
+            hook_gap: settings.hook_gap.unwrap_or(DEFAULT_HOOK_GAP),
+            notify,
+            notify_command: settings
+                .notify_command
+                .unwrap_or_else(|| DEFAULT_NOTIFY_COMMAND.to_string()),
+            keymap,
+            panes,
         })
     }
 }
@@ -91,67 +315,209 @@ impl Config {
 mod tests {
     use super::*;
     use crate::cli::Cli;
-    use std::sync::{Mutex, OnceLock};
-
-    fn env_lock() -> std::sync::MutexGuard<'static, ()> {
-        static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
-        LOCK.get_or_init(|| Mutex::new(())).lock().expect("env lock")
-    }
-
-    fn with_watch_interval_env<F>(value: Option<&str>, test: F)
-    where
-        F: FnOnce(),
-    {
-        let _guard = env_lock();
-        let previous = std::env::var("WATCH_INTERVAL").ok();
-        match value {
-            Some(val) => unsafe {
-                std::env::set_var("WATCH_INTERVAL", val);
-            },
-            None => unsafe {
-                std::env::remove_var("WATCH_INTERVAL");
-            },
-        }
-        test();
-        match previous {
-            Some(val) => unsafe {
-                std::env::set_var("WATCH_INTERVAL", val);
-            },
-            None => unsafe {
-                std::env::remove_var("WATCH_INTERVAL");
-            },
-        }
+
+    /// Builds the config without the real environment or config file.
+    fn build(cli: Cli) -> Result<Config, String> {
+        Config::from_cli_with(cli, &|_| None)
     }
 
     #[test]
     fn config_uses_default_interval_without_flag_or_env() {
-        with_watch_interval_env(None, || {
-            let cli = Cli::parse_from_iter(["watch", "echo", "hi"]).unwrap();
-            let config = Config::from_cli(cli).unwrap();
-            assert_eq!(config.interval, default_interval());
-        });
+        let cli = Cli::parse_from_iter(["watch", "echo", "hi"]).unwrap();
+        let config = build(cli).unwrap();
+        assert_eq!(config.interval, default_interval());
     }
 
     #[test]
     fn config_uses_watch_interval_env() {
-        with_watch_interval_env(Some("0.5"), || {
-            let cli = Cli::parse_from_iter(["watch", "echo", "hi"]).unwrap();
-            let config = Config::from_cli(cli).unwrap();
-            assert_eq!(config.interval, Duration::from_secs_f64(0.5));
-        });
+        let cli = Cli::parse_from_iter(["watch", "echo", "hi"]).unwrap();
+        let env = |key: &str| (key == "WATCH_INTERVAL").then(|| "0.5".to_string());
+        let config = Config::from_cli_with(cli, &env).unwrap();
+        assert_eq!(config.interval, Duration::from_secs_f64(0.5));
     }
 
     #[test]
     fn config_rejects_both_color_flags() {
         let cli = Cli::parse_from_iter(["watch", "-c", "-C", "echo", "hi"]).unwrap();
-        let err = Config::from_cli(cli).unwrap_err();
+        let err = build(cli).unwrap_err();
         assert!(err.contains("mutually exclusive"));
     }
 
     #[test]
     fn config_rejects_follow_with_tracking_options() {
         let cli = Cli::parse_from_iter(["watch", "-f", "-d", "echo", "hi"]).unwrap();
-        let err = Config::from_cli(cli).unwrap_err();
+        let err = build(cli).unwrap_err();
         assert!(err.contains("follow"));
     }
+
+    #[test]
+    fn config_rejects_cron_with_interval() {
+        let cli = Cli::parse_from_iter(["watch", "--cron", "* * * * *", "-n", "5", "echo"]).unwrap();
+        let err = build(cli).unwrap_err();
+        assert!(err.contains("--cron"));
+    }
+
+    #[test]
+    fn config_requires_watch_path_for_watch_options() {
+        let cli = Cli::parse_from_iter(["watch", "--watch-exclude", "*.swp", "make"]).unwrap();
+        let err = build(cli).unwrap_err();
+        assert!(err.contains("--watch-path"));
+    }
+
+    #[test]
+    fn config_requires_command_without_profile() {
+        let cli = Cli::parse_from_iter(["watch", "-n", "1"]).unwrap();
+        let err = Config::from_settings(Settings::from_cli(cli).unwrap()).unwrap_err();
+        assert!(err.contains("no command"));
+    }
+
+    #[test]
+    fn config_takes_command_from_profile() {
+        let file = ConfigFile::parse(
+            "[profiles.pods]\ncommand = \"kubectl get pods\"\n",
+            "config.toml",
+        )
+        .unwrap();
+        let cli = Cli::parse_from_iter(["watch", "--profile", "pods"]).unwrap();
+        let settings = file
+            .resolve(cli.profile.as_deref())
+            .unwrap()
+            .merge(Settings::from_cli(cli).unwrap());
+        let config = Config::from_settings(settings).unwrap();
+        assert_eq!(config.panes[0].command, vec!["kubectl get pods"]);
+    }
+
+    #[test]
+    fn config_rejects_conflicting_keys() {
+        let file = ConfigFile::parse("[keys]\nrerun = \"j\"\n", "config.toml").unwrap();
+        let cli = Cli::parse_from_iter(["watch", "date"]).unwrap();
+        let settings = file.defaults.merge(Settings::from_cli(cli).unwrap());
+        let err = Config::from_settings(settings).unwrap_err();
+        assert_eq!(err, "keys: key 'j' is bound to both scroll-down and rerun");
+    }
+
+    #[test]
+    fn config_rejects_shell_with_exec() {
+        let cli = Cli::parse_from_iter(["watch", "-x", "--shell", "bash", "ls"]).unwrap();
+        let err = build(cli).unwrap_err();
+        assert!(err.contains("--exec"));
+    }
+
+    #[test]
+    fn config_splits_commands_into_panes() {
+        let cli = Cli::parse_from_iter([
+            "watch", "--split", "h", "--", "date", ":::", "-n", "5", "uptime",
+        ])
+        .unwrap();
+        let config = build(cli).unwrap();
+        assert_eq!(config.panes.len(), 2);
+        assert_eq!(config.panes[1].command, vec!["uptime"]);
+        assert_eq!(config.panes[1].interval, Some(Duration::from_secs(5)));
+
+        let cli = Cli::parse_from_iter(["watch", "parallel", "echo", ":::", "a", "b"]).unwrap();
+        let config = build(cli).unwrap();
+        assert_eq!(config.panes.len(), 1);
+        assert_eq!(config.panes[0].command, vec!["parallel", "echo", ":::", "a", "b"]);
+
+        let cli = Cli::parse_from_iter(["watch", "-f", "--split", "v", "date", ":::", "uptime"])
+            .unwrap();
+        assert!(build(cli).unwrap_err().contains("--follow"));
+    }
+
+    #[test]
+    fn config_collects_hooks() {
+        let cli = Cli::parse_from_iter(["watch", "--on-fail", "notify", "--hook-gap", "5s", "date"])
+            .unwrap();
+        let config = build(cli).unwrap();
+        assert_eq!(config.hooks, vec![(HookEvent::Fail, "notify".to_string())]);
+        assert_eq!(config.hook_gap, Duration::from_secs(5));
+
+        let cli = Cli::parse_from_iter(["watch", "--hook-gap", "5s", "date"]).unwrap();
+        assert!(build(cli).unwrap_err().contains("--hook-gap"));
+    }
+
+    #[test]
+    fn config_requires_follow_for_log_options() {
+        let cli = Cli::parse_from_iter(["watch", "-f", "--separator", "--dedup", "date"]).unwrap();
+        let config = build(cli).unwrap();
+        assert!(config.separator && config.dedup);
+        let cli = Cli::parse_from_iter(["watch", "--dedup", "date"]).unwrap();
+        assert!(build(cli).unwrap_err().contains("--dedup requires --follow"));
+    }
+
+    #[test]
+    fn config_builds_filter() {
+        let cli = Cli::parse_from_iter([
+            "watch",
+            "--filter",
+            "ERR",
+            "--filter-context=2",
+            "--filter-invert",
+            "--filter-checks",
+            "full",
+            "dmesg",
+        ])
+        .unwrap();
+        let config = build(cli).unwrap();
+        let filter = config.filter.unwrap();
+        assert_eq!(filter.pattern.as_str(), "ERR");
+        assert_eq!((filter.context, filter.invert), (2, true));
+        assert_eq!(config.filter_checks, FilterChecks::Full);
+        assert!(Cli::parse_from_iter(["watch", "--filter", "(", "date"]).is_err());
+    }
+
+    #[test]
+    fn config_validates_tab_width() {
+        let cli = Cli::parse_from_iter(["watch", "date"]).unwrap();
+        assert_eq!(build(cli).unwrap().tab_width, DEFAULT_TAB_WIDTH);
+        let cli = Cli::parse_from_iter(["watch", "--tab-width", "0", "date"]).unwrap();
+        assert!(build(cli).unwrap_err().contains("--tab-width"));
+        let cli = Cli::parse_from_iter(["watch", "--tab-width", "1000000000", "date"]).unwrap();
+        assert!(build(cli).unwrap_err().contains("--tab-width"));
+        let cli = Cli::parse_from_iter(["watch", "--tab-width", "64", "date"]).unwrap();
+        assert_eq!(build(cli).unwrap().tab_width, 64);
+    }
+
+    #[test]
+    fn config_requires_desktop_rule_for_notify_command() {
+        let cli = Cli::parse_from_iter(["watch", "--notify-command", "dunstify", "date"]).unwrap();
+        assert!(build(cli).unwrap_err().contains("--notify"));
+        let cli = Cli::parse_from_iter([
+            "watch",
+            "--notify",
+            "desktop:fail",
+            "--notify-command",
+            "dunstify",
+            "date",
+        ])
+        .unwrap();
+        assert_eq!(build(cli).unwrap().notify_command, "dunstify");
+    }
+
+    #[test]
+    fn config_beep_means_beep_on_fail() {
+        let cli = Cli::parse_from_iter(["watch", "-b", "--beep-on", "change", "date"]).unwrap();
+        let config = build(cli).unwrap();
+        assert_eq!(config.beep_on, vec![Condition::Change, Condition::Fail]);
+        assert_eq!(config.bell_gap, DEFAULT_BELL_GAP);
+
+        let cli = Cli::parse_from_iter(["watch", "-b", "--bell-gap", "1m", "date"]).unwrap();
+        assert_eq!(build(cli).unwrap().bell_gap, Duration::from_secs(60));
+        let cli = Cli::parse_from_iter(["watch", "--bell-gap", "1m", "date"]).unwrap();
+        assert!(build(cli).unwrap_err().contains("--bell-gap"));
+    }
+
+    #[test]
+    fn config_rejects_missing_chdir() {
+        let cli = Cli::parse_from_iter(["watch", "--chdir", "/no/such/dir", "ls"]).unwrap();
+        let err = build(cli).unwrap_err();
+        assert!(err.contains("--chdir"));
+    }
+
+    #[test]
+    fn config_requires_shotsdir_for_shot_on() {
+        let cli = Cli::parse_from_iter(["watch", "--shot-on=fail", "echo", "hi"]).unwrap();
+        let err = build(cli).unwrap_err();
+        assert!(err.contains("--shotsdir"));
+    }
 }
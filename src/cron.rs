@@ -0,0 +1,277 @@
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDate, NaiveDateTime, Timelike};
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Searching further than this many years ahead means the expression never fires.
+const SEARCH_YEARS: i32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CronParseError {
+    FieldCount(usize),
+    InvalidField { field: &'static str, value: String },
+    NeverFires,
+}
+
+impl std::fmt::Display for CronParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CronParseError::FieldCount(count) => {
+                write!(f, "cron expression needs 5 fields, got {count}")
+            }
+            CronParseError::InvalidField { field, value } => {
+                write!(f, "invalid cron {field} field '{value}'")
+            }
+            CronParseError::NeverFires => write!(f, "cron expression never fires"),
+        }
+    }
+}
+
+impl std::error::Error for CronParseError {}
+
+struct FieldSpec {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    names: &'static [&'static str],
+    names_start: u32,
+}
+
+const MINUTE: FieldSpec = FieldSpec {
+    name: "minute",
+    min: 0,
+    max: 59,
+    names: &[],
+    names_start: 0,
+};
+const HOUR: FieldSpec = FieldSpec {
+    name: "hour",
+    min: 0,
+    max: 23,
+    names: &[],
+    names_start: 0,
+};
+const DAY_OF_MONTH: FieldSpec = FieldSpec {
+    name: "day-of-month",
+    min: 1,
+    max: 31,
+    names: &[],
+    names_start: 0,
+};
+const MONTH: FieldSpec = FieldSpec {
+    name: "month",
+    min: 1,
+    max: 12,
+    names: &MONTH_NAMES,
+    names_start: 1,
+};
+const DAY_OF_WEEK: FieldSpec = FieldSpec {
+    name: "day-of-week",
+    min: 0,
+    max: 7,
+    names: &DAY_NAMES,
+    names_start: 0,
+};
+
+pub fn parse_cron(input: &str) -> Result<CronSchedule, CronParseError> {
+    let trimmed = input.trim();
+    let expanded = match trimmed.to_ascii_lowercase().as_str() {
+        "@yearly" | "@annually" => "0 0 1 1 *",
+        "@monthly" => "0 0 1 * *",
+        "@weekly" => "0 0 * * 0",
+        "@daily" | "@midnight" => "0 0 * * *",
+        "@hourly" => "0 * * * *",
+        _ => trimmed,
+    };
+
+    let fields: Vec<&str> = expanded.split_whitespace().collect();
+    if fields.len() != 5 {
+        return Err(CronParseError::FieldCount(fields.len()));
+    }
+
+    let mut days_of_week = parse_field(fields[4], &DAY_OF_WEEK)?;
+    // Both 0 and 7 mean Sunday.
+    if days_of_week & (1 << 7) != 0 {
+        days_of_week = (days_of_week | 1) & !(1 << 7);
+    }
+
+    let schedule = CronSchedule {
+        source: trimmed.to_string(),
+        minutes: parse_field(fields[0], &MINUTE)?,
+        hours: parse_field(fields[1], &HOUR)?,
+        days_of_month: parse_field(fields[2], &DAY_OF_MONTH)?,
+        months: parse_field(fields[3], &MONTH)?,
+        days_of_week,
+        any_day_of_month: fields[2].starts_with('*'),
+        any_day_of_week: fields[4].starts_with('*'),
+    };
+
+    let probe = NaiveDate::from_ymd_opt(2000, 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("valid probe date");
+    if schedule.next_after(probe).is_none() {
+        return Err(CronParseError::NeverFires);
+    }
+    Ok(schedule)
+}
+
+fn parse_field(field: &str, spec: &FieldSpec) -> Result<u64, CronParseError> {
+    let invalid = || CronParseError::InvalidField {
+        field: spec.name,
+        value: field.to_string(),
+    };
+
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| invalid())?;
+                if step == 0 {
+                    return Err(invalid());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (spec.min, spec.max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_value(start, spec).ok_or_else(invalid)?,
+                parse_value(end, spec).ok_or_else(invalid)?,
+            )
+        } else {
+            let start = parse_value(range, spec).ok_or_else(invalid)?;
+            let end = if part.contains('/') { spec.max } else { start };
+            (start, end)
+        };
+
+        if start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(raw: &str, spec: &FieldSpec) -> Option<u32> {
+    let lower = raw.to_ascii_lowercase();
+    let value = match spec.names.iter().position(|name| *name == lower) {
+        Some(idx) => idx as u32 + spec.names_start,
+        None => raw.parse().ok()?,
+    };
+    (spec.min..=spec.max).contains(&value).then_some(value)
+}
+
+impl CronSchedule {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the first matching minute strictly after `after`.
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut candidate = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let limit_year = after.year() + SEARCH_YEARS;
+
+        while candidate.year() <= limit_year {
+            if !has_bit(self.months, candidate.month()) {
+                let (year, month) = if candidate.month() == 12 {
+                    (candidate.year() + 1, 1)
+                } else {
+                    (candidate.year(), candidate.month() + 1)
+                };
+                candidate = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.day_matches(candidate.date()) {
+                candidate = candidate.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !has_bit(self.hours, candidate.hour()) {
+                candidate = candidate.with_minute(0)? + ChronoDuration::hours(1);
+                continue;
+            }
+            if !has_bit(self.minutes, candidate.minute()) {
+                candidate += ChronoDuration::minutes(1);
+                continue;
+            }
+            return Some(candidate);
+        }
+        None
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let dom = has_bit(self.days_of_month, date.day());
+        let dow = has_bit(self.days_of_week, date.weekday().num_days_from_sunday());
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (true, false) => dow,
+            (false, true) => dom,
+            (false, false) => dom || dow,
+        }
+    }
+}
+
+fn has_bit(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, mo, d)
+            .unwrap()
+            .and_hms_opt(h, mi, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn every_five_minutes() {
+        let cron = parse_cron("*/5 * * * *").unwrap();
+        assert_eq!(cron.next_after(at(2024, 3, 1, 10, 7)), Some(at(2024, 3, 1, 10, 10)));
+        assert_eq!(cron.next_after(at(2024, 3, 1, 10, 55)), Some(at(2024, 3, 1, 11, 0)));
+    }
+
+    #[test]
+    fn weekdays_at_nine_with_names() {
+        let cron = parse_cron("0 9 * * mon-fri").unwrap();
+        // 2024-03-02 is a Saturday.
+        assert_eq!(cron.next_after(at(2024, 3, 2, 12, 0)), Some(at(2024, 3, 4, 9, 0)));
+    }
+
+    #[test]
+    fn day_of_month_or_day_of_week() {
+        let cron = parse_cron("0 0 15 * sun").unwrap();
+        // 2024-03-03 is a Sunday, before the 15th.
+        assert_eq!(cron.next_after(at(2024, 3, 1, 0, 0)), Some(at(2024, 3, 3, 0, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert_eq!(parse_cron("* * *").unwrap_err(), CronParseError::FieldCount(3));
+        assert!(matches!(
+            parse_cron("61 * * * *").unwrap_err(),
+            CronParseError::InvalidField { field: "minute", .. }
+        ));
+        assert_eq!(parse_cron("0 0 30 2 *").unwrap_err(), CronParseError::NeverFires);
+    }
+}
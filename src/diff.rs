@@ -1,10 +1,12 @@
-use similar::TextDiff;
+use similar::{ChangeTag, TextDiff};
+use unicode_segmentation::UnicodeSegmentation;
 
-use crate::config::DifferencesMode;
+use crate::grid::Grid;
 
-pub const HIGHLIGHT_START: &str = "\x1b[7m";
-pub const HIGHLIGHT_END: &str = "\x1b[0m";
+use crate::config::DifferencesMode;
 
+/// Remembers the visible text of earlier runs; escape sequences never
+/// count as changes.
 #[derive(Debug, Default, Clone)]
 pub struct DiffState {
     previous: Option<String>,
@@ -13,7 +15,7 @@ pub struct DiffState {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DiffResult {
-    pub text: String,
+    pub grid: Grid,
     pub changed: bool,
 }
 
@@ -22,82 +24,135 @@ impl DiffState {
         Self::default()
     }
 
-    pub fn apply(&mut self, current: &str, mode: Option<DifferencesMode>) -> DiffResult {
-        let changed = self
-            .previous
-            .as_deref()
-            .map(|prev| prev != current)
-            .unwrap_or(false);
-
-        let highlighted = match mode {
-            None => current.to_string(),
-            Some(DifferencesMode::Changes) => match self.previous.as_deref() {
-                Some(prev) => highlight_diff(prev, current),
-                None => current.to_string(),
-            },
+    pub fn apply(&mut self, current: &Grid, mode: Option<DifferencesMode>) -> DiffResult {
+        let text = current.text();
+        let changed = self.previous.as_deref().is_some_and(|prev| prev != text);
+
+        let base = match mode {
+            None => None,
+            Some(DifferencesMode::Changes) => self.previous.as_deref(),
             Some(DifferencesMode::Permanent) => {
-                if self.baseline.is_none() {
-                    self.baseline = Some(current.to_string());
-                    current.to_string()
-                } else {
-                    highlight_diff(self.baseline.as_deref().unwrap_or(""), current)
-                }
+                self.baseline.get_or_insert_with(|| text.clone());
+                self.baseline.as_deref()
             }
         };
+        let grid = match base {
+            Some(base) => highlight_diff(base, current),
+            None => current.clone(),
+        };
 
-        self.previous = Some(current.to_string());
+        self.previous = Some(text);
 
-        DiffResult {
-            text: highlighted,
-            changed,
-        }
+        DiffResult { grid, changed }
     }
 }
 
-pub fn highlight_diff(base: &str, current: &str) -> String {
-    let diff = TextDiff::from_chars(base, current);
-    let mut out = String::new();
+/// Flips reverse video on the cells of `current` whose text is not in
+/// `base`, keeping the colors they already have. Text is compared by
+/// grapheme cluster, so a cluster is highlighted whole or not at all.
+pub fn highlight_diff(base: &str, current: &Grid) -> Grid {
+    // The current text's clusters and the cell each lives in; `None` for
+    // newlines.
+    let mut graphemes = Vec::new();
+    let mut positions = Vec::new();
+    for (row, line) in current.lines.iter().enumerate() {
+        if row > 0 {
+            graphemes.push("\n");
+            positions.push(None);
+        }
+        for (col, cell) in line.cells.iter().enumerate() {
+            for grapheme in cell.text.graphemes(true) {
+                graphemes.push(grapheme);
+                positions.push(Some((row, col)));
+            }
+        }
+    }
+
+    let base: Vec<&str> = base.graphemes(true).collect();
+    let diff = TextDiff::from_slices(&base, &graphemes);
+    let mut highlighted = vec![Vec::new(); current.lines.len()];
+    let mut next = positions.into_iter();
     for change in diff.iter_all_changes() {
-        match change.tag() {
-            similar::ChangeTag::Equal => out.push_str(change.value()),
-            similar::ChangeTag::Insert => {
-                out.push_str(HIGHLIGHT_START);
-                out.push_str(change.value());
-                out.push_str(HIGHLIGHT_END);
+        let position = match change.tag() {
+            ChangeTag::Delete => continue,
+            ChangeTag::Equal => {
+                next.next();
+                continue;
             }
-            similar::ChangeTag::Delete => {}
+            ChangeTag::Insert => next.next().flatten(),
+        };
+        if let Some((row, col)) = position
+            && highlighted[row].last() != Some(&col)
+        {
+            highlighted[row].push(col);
+        }
+    }
+
+    let mut grid = current.clone();
+    for (line, cols) in grid.lines.iter_mut().zip(highlighted) {
+        for col in cols {
+            let style = &mut line.cells[col].style;
+            style.reverse = !style.reverse;
         }
     }
-    out
+    grid
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn highlighted(base: &str, current: &str) -> String {
+        highlight_diff(base, &Grid::parse(current)).lines[0].to_ansi()
+    }
+
     #[test]
     fn highlight_marks_inserted_chars() {
-        let out = highlight_diff("abc", "abXc");
-        assert!(out.contains(HIGHLIGHT_START));
-        assert!(out.contains("X"));
-        assert!(out.contains(HIGHLIGHT_END));
+        assert_eq!(highlighted("abc", "abXc"), "ab\x1b[7mX\x1b[0mc");
+    }
+
+    #[test]
+    fn highlight_never_splits_clusters() {
+        // Same base letter, different combining mark.
+        assert_eq!(highlighted("ae\u{301}", "ae\u{300}"), "a\x1b[7me\u{300}\x1b[0m");
+        let family = "\u{1f468}\u{200d}\u{1f469}";
+        let couple = "\u{1f468}\u{200d}\u{1f468}";
+        assert_eq!(
+            highlighted(&format!("{family}x"), &format!("{couple}x")),
+            format!("\x1b[7m{couple}\x1b[0mx")
+        );
+    }
+
+    #[test]
+    fn highlight_keeps_colors_and_ignores_escapes() {
+        let out = highlighted("ab", "\x1b[32mab\x1b[0mX");
+        assert_eq!(out, "\x1b[32mab\x1b[0;7mX\x1b[0m");
+    }
+
+    #[test]
+    fn diff_state_ignores_color_only_changes() {
+        let mut state = DiffState::new();
+        let _ = state.apply(&Grid::parse("\x1b[31mred"), Some(DifferencesMode::Changes));
+        let out = state.apply(&Grid::parse("\x1b[32mred"), Some(DifferencesMode::Changes));
+        assert!(!out.changed);
+        assert_eq!(out.grid.lines[0].to_ansi(), "\x1b[32mred\x1b[0m");
     }
 
     #[test]
     fn diff_state_tracks_changes() {
         let mut state = DiffState::new();
-        let first = state.apply("a", Some(DifferencesMode::Changes));
+        let first = state.apply(&Grid::parse("a"), Some(DifferencesMode::Changes));
         assert!(!first.changed);
-        let second = state.apply("b", Some(DifferencesMode::Changes));
+        let second = state.apply(&Grid::parse("b"), Some(DifferencesMode::Changes));
         assert!(second.changed);
     }
 
     #[test]
     fn cumulative_mode_uses_baseline() {
         let mut state = DiffState::new();
-        let _ = state.apply("abc", Some(DifferencesMode::Permanent));
-        let out = state.apply("abXc", Some(DifferencesMode::Permanent));
-        assert!(out.text.contains("X"));
-        assert!(out.text.contains(HIGHLIGHT_START));
+        let _ = state.apply(&Grid::parse("abc"), Some(DifferencesMode::Permanent));
+        let _ = state.apply(&Grid::parse("abc"), Some(DifferencesMode::Permanent));
+        let out = state.apply(&Grid::parse("abXc"), Some(DifferencesMode::Permanent));
+        assert_eq!(out.grid.lines[0].to_ansi(), "ab\x1b[7mX\x1b[0mc");
     }
 }
@@ -1,5 +1,9 @@
+use std::borrow::Cow;
 use std::ffi::OsString;
-use std::process::{Command, ExitStatus};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::Duration;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Shell {
@@ -7,6 +11,73 @@ pub struct Shell {
     pub args: Vec<OsString>,
 }
 
+/// Quoting rules used when joining multiple command arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellFamily {
+    /// sh, bash, zsh, dash, ksh and other POSIX shells.
+    Posix,
+    Fish,
+    PowerShell,
+    Cmd,
+}
+
+impl ShellFamily {
+    pub fn detect(program: &Path) -> Self {
+        let name = program
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match name.as_str() {
+            "fish" => ShellFamily::Fish,
+            "pwsh" | "powershell" => ShellFamily::PowerShell,
+            "cmd" => ShellFamily::Cmd,
+            _ => ShellFamily::Posix,
+        }
+    }
+
+    fn default_args(self) -> Vec<OsString> {
+        match self {
+            ShellFamily::Posix | ShellFamily::Fish => vec![OsString::from("-c")],
+            ShellFamily::PowerShell => {
+                vec![OsString::from("-NoProfile"), OsString::from("-Command")]
+            }
+            ShellFamily::Cmd => vec![OsString::from("/C")],
+        }
+    }
+}
+
+impl Shell {
+    /// Uses `program` with the given arguments, or with the usual
+    /// "run this string" flag of its family when `args` is empty.
+    pub fn new(program: &str, args: &[String]) -> Self {
+        let program = OsString::from(program);
+        let args = if args.is_empty() {
+            ShellFamily::detect(Path::new(&program)).default_args()
+        } else {
+            args.iter().map(OsString::from).collect()
+        };
+        Self { program, args }
+    }
+
+    pub fn family(&self) -> ShellFamily {
+        ShellFamily::detect(Path::new(&self.program))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecOptions {
+    /// Shell used to interpret the command; `None` runs it directly (`--exec`).
+    pub shell: Option<Shell>,
+    pub chdir: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    /// Start from an empty environment, keeping only what is needed to find
+    /// programs.
+    pub env_clear: bool,
+}
+
+/// Variables that survive `--env-clear`.
+const KEPT_ENV_VARS: &[&str] = &["PATH", "SystemRoot", "SYSTEMROOT"];
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExecOutput {
     pub stdout: Vec<u8>,
@@ -28,6 +99,7 @@ pub enum ExecError {
     MissingCommand,
     ShellNotFound,
     SpawnFailed(String),
+    StdinFailed(String),
 }
 
 impl std::fmt::Display for ExecError {
@@ -36,6 +108,7 @@ impl std::fmt::Display for ExecError {
             ExecError::MissingCommand => write!(f, "missing command"),
             ExecError::ShellNotFound => write!(f, "shell not found"),
             ExecError::SpawnFailed(err) => write!(f, "failed to run command: {err}"),
+            ExecError::StdinFailed(err) => write!(f, "failed to read command input: {err}"),
         }
     }
 }
@@ -82,23 +155,124 @@ pub fn default_shell() -> Result<Shell, ExecError> {
     }
 }
 
-pub fn build_command(command: &[String], exec: bool) -> Result<Command, ExecError> {
+/// Picks `--shell` when given, otherwise the platform default.
+pub fn select_shell(program: Option<&str>, args: &[String]) -> Result<Shell, ExecError> {
+    match program {
+        Some(program) if program.trim().is_empty() => Err(ExecError::ShellNotFound),
+        Some(program) => Ok(Shell::new(program, args)),
+        None => {
+            let mut shell = default_shell()?;
+            if !args.is_empty() {
+                shell.args = args.iter().map(OsString::from).collect();
+            }
+            Ok(shell)
+        }
+    }
+}
+
+pub fn build_command(command: &[String], options: &ExecOptions) -> Result<Command, ExecError> {
     if command.is_empty() {
         return Err(ExecError::MissingCommand);
     }
 
-    if exec {
-        let mut cmd = Command::new(&command[0]);
-        cmd.args(&command[1..]);
-        return Ok(cmd);
+    let mut cmd = match &options.shell {
+        Some(shell) => {
+            let family = shell.family();
+            let command_str = join_command(command, family);
+            let mut cmd = Command::new(&shell.program);
+            cmd.args(&shell.args);
+            push_command_arg(&mut cmd, command_str, family);
+            cmd
+        }
+        None => {
+            let mut cmd = Command::new(&command[0]);
+            cmd.args(&command[1..]);
+            cmd
+        }
+    };
+
+    if let Some(dir) = &options.chdir {
+        cmd.current_dir(dir);
+    }
+    if options.env_clear {
+        cmd.env_clear();
+        for key in KEPT_ENV_VARS {
+            if let Some(value) = std::env::var_os(key) {
+                cmd.env(key, value);
+            }
+        }
+    }
+    cmd.envs(options.env.iter().map(|(key, value)| (key, value)));
+    Ok(cmd)
+}
+
+/// Exports details about the current run so scripts can tell they are being
+/// watched.
+pub fn set_run_env(
+    cmd: &mut Command,
+    run_number: u64,
+    interval: Option<Duration>,
+    prev_exit: Option<i32>,
+) {
+    cmd.env("WATCH_RUN_NUMBER", run_number.to_string());
+    match interval {
+        Some(interval) => cmd.env("WATCH_RUN_INTERVAL", interval.as_secs_f64().to_string()),
+        None => cmd.env_remove("WATCH_RUN_INTERVAL"),
+    };
+    match prev_exit {
+        Some(code) => cmd.env("WATCH_PREV_EXIT", code.to_string()),
+        None => cmd.env_remove("WATCH_PREV_EXIT"),
+    };
+}
+
+#[cfg(windows)]
+fn push_command_arg(cmd: &mut Command, command_str: String, family: ShellFamily) {
+    use std::os::windows::process::CommandExt;
+
+    // cmd.exe does not understand the MSVC escaping applied to normal args.
+    if family == ShellFamily::Cmd {
+        cmd.raw_arg(command_str);
+    } else {
+        cmd.arg(command_str);
     }
+}
 
-    let shell = default_shell()?;
-    let command_str = command.join(" ");
-    let mut cmd = Command::new(shell.program);
-    cmd.args(shell.args);
+#[cfg(not(windows))]
+fn push_command_arg(cmd: &mut Command, command_str: String, _family: ShellFamily) {
     cmd.arg(command_str);
-    Ok(cmd)
+}
+
+/// Joins command arguments into one shell command line. A single argument is
+/// passed through untouched, like GNU watch. With several arguments, those
+/// containing whitespace or quotes are quoted for the shell family, while
+/// other shell syntax such as pipes or globs keeps working.
+pub fn join_command(command: &[String], family: ShellFamily) -> String {
+    if command.len() == 1 {
+        return command[0].clone();
+    }
+    command
+        .iter()
+        .map(|arg| quote_arg(arg, family))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn quote_arg(arg: &str, family: ShellFamily) -> Cow<'_, str> {
+    let needs_quotes = arg.is_empty()
+        || arg
+            .chars()
+            .any(|ch| ch.is_whitespace() || matches!(ch, '\'' | '"' | '`'));
+    if !needs_quotes {
+        return Cow::Borrowed(arg);
+    }
+
+    let quoted = match family {
+        ShellFamily::Posix => format!("'{}'", arg.replace('\'', "'\\''")),
+        ShellFamily::Fish => format!("'{}'", arg.replace('\\', "\\\\").replace('\'', "\\'")),
+        ShellFamily::PowerShell => format!("'{}'", arg.replace('\'', "''")),
+        ShellFamily::Cmd => format!("\"{}\"", arg.replace('"', "\"\"")),
+    };
+    Cow::Owned(quoted)
 }
 
 pub fn run_command(mut command: Command) -> Result<ExecOutput, ExecError> {
@@ -112,11 +286,41 @@ pub fn run_command(mut command: Command) -> Result<ExecOutput, ExecError> {
     })
 }
 
+/// Like `run_command`, feeding `input` to the command's stdin.
+pub fn run_command_with_input(mut command: Command, input: &[u8]) -> Result<ExecOutput, ExecError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| ExecError::SpawnFailed(err.to_string()))?;
+
+    let output = std::thread::scope(|scope| {
+        if let Some(mut stdin) = child.stdin.take() {
+            // Writing from a thread avoids a deadlock when the command fills
+            // its output pipes before reading all of its input.
+            scope.spawn(move || {
+                let _ = stdin.write_all(input);
+            });
+        }
+        child.wait_with_output()
+    })
+    .map_err(|err| ExecError::SpawnFailed(err.to_string()))?;
+
+    Ok(ExecOutput {
+        stdout: output.stdout,
+        stderr: output.stderr,
+        status: output.status,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(windows)]
     use std::sync::{Mutex, OnceLock};
 
+    #[cfg(windows)]
     fn env_lock() -> std::sync::MutexGuard<'static, ()> {
         static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
         LOCK.get_or_init(|| Mutex::new(())).lock().expect("env lock")
@@ -124,10 +328,71 @@ mod tests {
 
     #[test]
     fn build_exec_command_uses_first_arg_as_program() {
-        let cmd = build_command(&["echo".into(), "hi".into()], true).unwrap();
+        let cmd = build_command(&["echo".into(), "hi".into()], &ExecOptions::default()).unwrap();
         assert_eq!(cmd.get_program(), "echo");
     }
 
+    #[test]
+    fn detects_shell_family_from_program() {
+        for (program, family) in [
+            ("/usr/bin/zsh", ShellFamily::Posix),
+            ("/usr/local/bin/fish", ShellFamily::Fish),
+            ("pwsh.exe", ShellFamily::PowerShell),
+            ("C:/Windows/System32/cmd.exe", ShellFamily::Cmd),
+        ] {
+            assert_eq!(ShellFamily::detect(Path::new(program)), family);
+        }
+    }
+
+    #[test]
+    fn joins_and_quotes_for_each_family() {
+        let command: Vec<String> = vec!["echo".into(), "a b".into(), "it's".into(), "|".into()];
+        for (family, expected) in [
+            (ShellFamily::Posix, "echo 'a b' 'it'\\''s' |"),
+            (ShellFamily::Fish, "echo 'a b' 'it\\'s' |"),
+            (ShellFamily::PowerShell, "echo 'a b' 'it''s' |"),
+            (ShellFamily::Cmd, "echo \"a b\" \"it's\" |"),
+        ] {
+            assert_eq!(join_command(&command, family), expected);
+        }
+        assert_eq!(join_command(&["ls | wc".into()], ShellFamily::Posix), "ls | wc");
+    }
+
+    #[test]
+    fn leaves_expansions_to_the_shell() {
+        let command: Vec<String> =
+            vec!["ls".into(), "*.log".into(), "$HOME".into(), ">out.txt".into()];
+        assert_eq!(join_command(&command, ShellFamily::Posix), "ls *.log $HOME >out.txt");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn command_gets_directory_environment_and_input() {
+        let options = ExecOptions {
+            shell: Some(Shell::new("/bin/sh", &[])),
+            chdir: Some(PathBuf::from("/")),
+            env: vec![("GREETING".into(), "hi".into())],
+            env_clear: true,
+        };
+        let script = "printf '%s %s %s %s %s ' \"$PWD\" \"$GREETING\" \"$WATCH_RUN_NUMBER\" \"$WATCH_RUN_INTERVAL\" \"$HOME\"; cat";
+        let mut cmd = build_command(&[script.into()], &options).unwrap();
+        set_run_env(&mut cmd, 3, Some(Duration::from_secs(2)), Some(1));
+        let output = run_command_with_input(cmd, b"input").unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "/ hi 3 2  input");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn shell_command_keeps_spaces_in_arguments() {
+        let options = ExecOptions {
+            shell: Some(Shell::new("/bin/sh", &[])),
+            ..ExecOptions::default()
+        };
+        let cmd = build_command(&["printf".into(), "%s.".into(), "a b".into()], &options).unwrap();
+        let output = run_command(cmd).unwrap();
+        assert_eq!(output.stdout, b"a b.");
+    }
+
     #[cfg(windows)]
     #[test]
     fn default_shell_prefers_comspec_when_shell_unset() {
@@ -0,0 +1,103 @@
+use crate::grid::{Grid, Line};
+use crate::pattern::Pattern;
+
+/// Which output change detection, exit conditions and hooks look at while
+/// a filter is active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterChecks {
+    /// The lines the filter keeps, as if the command were piped to grep.
+    Filtered,
+    /// Everything the command printed.
+    Full,
+}
+
+pub fn parse_filter_checks(value: &str) -> Result<FilterChecks, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "filtered" => Ok(FilterChecks::Filtered),
+        "full" => Ok(FilterChecks::Full),
+        other => Err(format!(
+            "unknown filter checks '{other}', expected 'filtered' or 'full'"
+        )),
+    }
+}
+
+/// Keeps the output lines matching a pattern, like `grep`, but with the
+/// command's colors intact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub pattern: Pattern,
+    /// Lines kept before and after each match.
+    pub context: usize,
+    /// Keep the lines that do not match instead.
+    pub invert: bool,
+}
+
+impl Filter {
+    /// The kept lines of `grid`. As with grep, `--` separates groups of
+    /// lines that are not adjacent when context is shown.
+    pub fn apply(&self, grid: &Grid) -> Grid {
+        let hits: Vec<usize> = grid
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| self.pattern.is_match(&line.to_plain()) != self.invert)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        let mut lines = Vec::new();
+        let mut next = 0;
+        for hit in hits {
+            let start = hit.saturating_sub(self.context).max(next);
+            let end = (hit + self.context + 1).min(grid.lines.len());
+            if start >= end {
+                continue;
+            }
+            if self.context > 0 && next > 0 && start > next {
+                lines.push(Line::plain("--"));
+            }
+            lines.extend(grid.lines[start..end].iter().cloned());
+            next = end;
+        }
+        Grid { lines }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(pattern: &str, context: usize, invert: bool) -> Filter {
+        Filter {
+            pattern: Pattern::new(pattern).unwrap(),
+            context,
+            invert,
+        }
+    }
+
+    #[test]
+    fn keeps_matching_lines_with_colors() {
+        let grid = Grid::parse("ok\n\x1b[31mERROR\x1b[0m disk\nok\nERROR net");
+        let kept = filter("ERROR", 0, false).apply(&grid);
+        assert_eq!(kept.text(), "ERROR disk\nERROR net");
+        assert_eq!(kept.lines[0].to_ansi(), "\x1b[31mERROR\x1b[0m disk");
+        assert_eq!(filter("ERROR", 0, true).apply(&grid).text(), "ok\nok");
+    }
+
+    #[test]
+    fn shows_context_and_separates_groups() {
+        let grid = Grid::parse("1\n2\nx\n4\n5\n6\n7\nx\n9");
+        assert_eq!(
+            filter("x", 1, false).apply(&grid).text(),
+            "2\nx\n4\n--\n7\nx\n9"
+        );
+        // Overlapping context is not repeated.
+        let grid = Grid::parse("x\n2\nx");
+        assert_eq!(filter("x", 2, false).apply(&grid).text(), "x\n2\nx");
+    }
+
+    #[test]
+    fn parses_filter_checks() {
+        assert_eq!(parse_filter_checks("Full").unwrap(), FilterChecks::Full);
+        assert!(parse_filter_checks("some").is_err());
+    }
+}
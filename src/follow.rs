@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Local};
+
+use crate::ansi::Style;
+use crate::filter::Filter;
+use crate::grid::{Cell, Grid, Line};
+
+/// Output lines kept for scrolling back in `--follow` mode; the oldest runs
+/// are dropped beyond this.
+pub const MAX_FOLLOW_LINES: usize = 10_000;
+
+/// The output of one run, or of several identical ones with `--dedup`.
+#[derive(Debug, Clone)]
+struct Entry {
+    started: DateTime<Local>,
+    last: DateTime<Local>,
+    repeats: u32,
+    text: String,
+    lines: Vec<Line>,
+}
+
+/// Everything `--follow` has appended so far.
+#[derive(Debug, Clone, Default)]
+pub struct FollowLog {
+    entries: VecDeque<Entry>,
+    separators: bool,
+    dedup: bool,
+    line_count: usize,
+}
+
+impl FollowLog {
+    pub fn new(separators: bool, dedup: bool) -> Self {
+        Self {
+            separators,
+            dedup,
+            ..Self::default()
+        }
+    }
+
+    /// Appends the output of a run. With dedup, a run printing the same as
+    /// the one before is only counted; returns whether anything was added.
+    pub fn push(&mut self, grid: &Grid, at: DateTime<Local>) -> bool {
+        let mut lines = grid.lines.clone();
+        while lines.last().is_some_and(|line| line.cells.is_empty()) {
+            lines.pop();
+        }
+        let text = grid.text();
+        if self.dedup
+            && let Some(last) = self.entries.back_mut()
+            && last.text == text
+        {
+            last.repeats += 1;
+            last.last = at;
+            return false;
+        }
+
+        self.line_count += lines.len();
+        self.entries.push_back(Entry {
+            started: at,
+            last: at,
+            repeats: 1,
+            text,
+            lines,
+        });
+        while self.line_count > MAX_FOLLOW_LINES && self.entries.len() > 1 {
+            if let Some(dropped) = self.entries.pop_front() {
+                self.line_count -= dropped.lines.len();
+            }
+        }
+        true
+    }
+
+    /// The log as one grid, with separators fitted to `columns`. A filter
+    /// applies to each run's lines and leaves the separators alone.
+    pub fn grid(&self, columns: usize, filter: Option<&Filter>) -> Grid {
+        let mut lines = Vec::with_capacity(self.line_count + self.entries.len());
+        for entry in &self.entries {
+            if self.separators {
+                lines.push(separator_line(entry, columns));
+            }
+            match filter {
+                Some(filter) => {
+                    let run = Grid {
+                        lines: entry.lines.clone(),
+                    };
+                    lines.extend(filter.apply(&run).lines);
+                }
+                None => lines.extend(entry.lines.iter().cloned()),
+            }
+        }
+        Grid { lines }
+    }
+}
+
+/// A dim rule with the time of the run, e.g. `── 12:00:01 ─────`, noting
+/// how often the output repeated with `--dedup`.
+fn separator_line(entry: &Entry, columns: usize) -> Line {
+    let mut label = format!("\u{2500}\u{2500} {} ", entry.started.format("%H:%M:%S"));
+    if entry.repeats > 1 {
+        label.push_str(&format!(
+            "(unchanged \u{d7}{}, last {}) ",
+            entry.repeats,
+            entry.last.format("%H:%M:%S")
+        ));
+    }
+    let style = Style {
+        dim: true,
+        ..Style::default()
+    };
+    let mut line = Line::styled(&label, style);
+    while line.width() < columns {
+        line.push(Cell::new('\u{2500}', style));
+    }
+    Grid { lines: vec![line] }
+        .wrap(columns, true)
+        .remove(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(second: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 1, 2, 12, 0, second).unwrap()
+    }
+
+    fn plain(rows: &[Line]) -> Vec<String> {
+        rows.iter().map(Line::to_plain).collect()
+    }
+
+    #[test]
+    fn appends_runs_with_separators() {
+        let mut log = FollowLog::new(true, false);
+        assert!(log.push(&Grid::parse("one\n"), at(1)));
+        assert!(log.push(&Grid::parse("one\n"), at(2)));
+        assert_eq!(
+            plain(&log.grid(16, None).lines),
+            vec![
+                "── 12:00:01 ────",
+                "one",
+                "── 12:00:02 ────",
+                "one"
+            ]
+        );
+    }
+
+    #[test]
+    fn dedup_counts_repeated_runs() {
+        let mut log = FollowLog::new(true, true);
+        log.push(&Grid::parse("same"), at(1));
+        assert!(!log.push(&Grid::parse("same"), at(2)));
+        assert!(!log.push(&Grid::parse("same"), at(3)));
+        assert!(log.push(&Grid::parse("new"), at(4)));
+        assert_eq!(
+            plain(&log.grid(80, None).lines)[0].trim_end_matches('─'),
+            "── 12:00:01 (unchanged ×3, last 12:00:03) "
+        );
+        assert_eq!(log.grid(80, None).lines.len(), 4);
+    }
+
+    #[test]
+    fn drops_oldest_runs_beyond_limit() {
+        let mut log = FollowLog::new(false, false);
+        let big = "x\n".repeat(MAX_FOLLOW_LINES / 2 + 1);
+        log.push(&Grid::parse(&big), at(1));
+        log.push(&Grid::parse(&big), at(2));
+        log.push(&Grid::parse("last"), at(3));
+        let lines = log.grid(10, None).lines;
+        assert_eq!(lines.len(), MAX_FOLLOW_LINES / 2 + 2);
+        assert_eq!(lines.last().unwrap().to_plain(), "last");
+    }
+}
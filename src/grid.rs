@@ -0,0 +1,341 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::ansi::{AnsiAction, Parser, Style};
+
+/// One character cell: the grapheme cluster drawn there, how many columns
+/// it takes and its style. Combining marks, ZWJ emoji sequences and flags
+/// stay in one cell; stray zero-width characters join the cell before them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub text: String,
+    pub width: usize,
+    pub style: Style,
+}
+
+impl Cell {
+    pub fn new(ch: char, style: Style) -> Self {
+        Self {
+            text: ch.to_string(),
+            width: UnicodeWidthChar::width(ch).unwrap_or(0),
+            style,
+        }
+    }
+
+    /// Adds `ch` to this cell if it continues the grapheme cluster, or
+    /// has no width of its own.
+    fn absorb(&mut self, ch: &str) -> bool {
+        let mut joined = self.text.clone();
+        joined.push_str(ch);
+        if joined.graphemes(true).nth(1).is_some() && UnicodeWidthStr::width(ch) > 0 {
+            return false;
+        }
+        self.width = UnicodeWidthStr::width(joined.as_str());
+        self.text = joined;
+        true
+    }
+}
+
+/// A row of cells, either a whole line of output or one screen row.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Line {
+    pub cells: Vec<Cell>,
+}
+
+impl Line {
+    /// A line of `text` in the given style. Escape sequences are not
+    /// interpreted.
+    pub fn styled(text: &str, style: Style) -> Self {
+        let mut line = Line::default();
+        for ch in text.chars().filter(|ch| !ch.is_control()) {
+            line.push(Cell::new(ch, style));
+        }
+        line
+    }
+
+    pub fn plain(text: &str) -> Self {
+        Self::styled(text, Style::default())
+    }
+
+    /// Appends `cell`, joining it to the previous cell when it continues
+    /// that cell's grapheme cluster.
+    pub fn push(&mut self, cell: Cell) {
+        if let Some(last) = self.cells.last_mut()
+            && last.absorb(&cell.text)
+        {
+            return;
+        }
+        if cell.width > 0 {
+            self.cells.push(cell);
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.iter().map(|cell| cell.width).sum()
+    }
+
+    /// Pads with blank cells up to `width` columns.
+    pub fn pad(&mut self, width: usize) {
+        let missing = width.saturating_sub(self.width());
+        self.cells
+            .extend((0..missing).map(|_| Cell::new(' ', Style::default())));
+    }
+
+    pub fn extend(&mut self, other: Line) {
+        self.cells.extend(other.cells);
+    }
+
+    pub fn to_plain(&self) -> String {
+        self.cells.iter().map(|cell| cell.text.as_str()).collect()
+    }
+
+    /// The line with the escape sequences needed to draw its styles, always
+    /// ending in the default style.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        let mut current = Style::default();
+        for cell in &self.cells {
+            out.push_str(&cell.style.transition(&current));
+            current = cell.style;
+            out.push_str(&cell.text);
+        }
+        out.push_str(&Style::default().transition(&current));
+        out
+    }
+}
+
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+pub const MAX_TAB_WIDTH: usize = 64;
+
+#[derive(Debug, Clone)]
+enum Slot {
+    Cell(Cell),
+    /// The second column of a wide character.
+    Continuation,
+}
+
+/// Builds one line column by column so that text can be overwritten after
+/// a carriage return or backspace.
+#[derive(Debug, Default)]
+struct LineWriter {
+    slots: Vec<Slot>,
+    cursor: usize,
+}
+
+impl LineWriter {
+    fn put(&mut self, cell: Cell) {
+        // Text continuing the cluster right before the cursor joins it. The
+        // cluster is written again as its width may change, e.g. with an
+        // emoji variation selector.
+        let before = self.slots[..self.cursor.min(self.slots.len())]
+            .iter()
+            .rposition(|slot| matches!(slot, Slot::Cell(_)));
+        if let Some(start) = before
+            && let Slot::Cell(previous) = &self.slots[start]
+            && start + previous.width == self.cursor
+        {
+            let mut joined = previous.clone();
+            if joined.absorb(&cell.text) {
+                self.cursor = start;
+                self.write(joined);
+                return;
+            }
+        }
+        if cell.width == 0 {
+            return;
+        }
+        self.write(cell);
+    }
+
+    /// Writes `cell` at the cursor, overwriting what is there.
+    fn write(&mut self, cell: Cell) {
+        let end = self.cursor + cell.width;
+        while self.slots.len() < end {
+            self.slots.push(Slot::Cell(Cell::new(' ', Style::default())));
+        }
+        // The other half of a wide character that is partly overwritten
+        // becomes blank.
+        if self.cursor > 0 && matches!(self.slots[self.cursor], Slot::Continuation) {
+            self.slots[self.cursor - 1] = Slot::Cell(Cell::new(' ', Style::default()));
+        }
+        if let Some(slot @ Slot::Continuation) = self.slots.get_mut(end) {
+            *slot = Slot::Cell(Cell::new(' ', Style::default()));
+        }
+
+        self.slots[self.cursor] = Slot::Cell(cell);
+        for slot in &mut self.slots[self.cursor + 1..end] {
+            *slot = Slot::Continuation;
+        }
+        self.cursor = end;
+    }
+
+    fn finish(self) -> Line {
+        let cells = self
+            .slots
+            .into_iter()
+            .filter_map(|slot| match slot {
+                Slot::Cell(cell) => Some(cell),
+                Slot::Continuation => None,
+            })
+            .collect();
+        Line { cells }
+    }
+}
+
+/// Command output as styled lines, before wrapping to the screen width.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Grid {
+    pub lines: Vec<Line>,
+}
+
+impl Grid {
+    pub fn parse(input: &str) -> Self {
+        Self::parse_with_tab_width(input, DEFAULT_TAB_WIDTH)
+    }
+
+    /// Parses `input` the way a terminal would draw it: `\r` returns to
+    /// the start of the line and overwrites, backspace moves left, and tabs
+    /// advance to the next multiple of `tab_width`.
+    pub fn parse_with_tab_width(input: &str, tab_width: usize) -> Self {
+        let tab_width = tab_width.clamp(1, MAX_TAB_WIDTH);
+        let mut parser = Parser::new();
+        let mut style = Style::default();
+        let mut lines = Vec::new();
+        let mut writer = LineWriter::default();
+        for ch in input.chars() {
+            match parser.feed(ch) {
+                Some(AnsiAction::Print(ch)) => writer.put(Cell::new(ch, style)),
+                Some(AnsiAction::Control('\n')) => {
+                    lines.push(std::mem::take(&mut writer).finish());
+                }
+                Some(AnsiAction::Control('\r')) => writer.cursor = 0,
+                Some(AnsiAction::Control('\x08')) => {
+                    writer.cursor = writer.cursor.saturating_sub(1);
+                }
+                Some(AnsiAction::Control('\t')) => {
+                    writer.cursor = (writer.cursor / tab_width + 1) * tab_width;
+                }
+                Some(AnsiAction::Sgr(params)) => style.apply_sgr(&params),
+                Some(AnsiAction::Control(_)) | None => {}
+            }
+        }
+        lines.push(writer.finish());
+        Self { lines }
+    }
+
+    /// The visible text, lines joined with `\n`.
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(Line::to_plain)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn clear_styles(&mut self) {
+        for cell in self.lines.iter_mut().flat_map(|line| line.cells.iter_mut()) {
+            cell.style = Style::default();
+        }
+    }
+
+    /// Breaks lines into screen rows of at most `width` columns, or cuts
+    /// them off at `width` with `no_wrap`. Wide characters never straddle
+    /// two rows.
+    pub fn wrap(&self, width: usize, no_wrap: bool) -> Vec<Line> {
+        self.wrap_indexed(width, no_wrap)
+            .into_iter()
+            .map(|(_, _, row)| row)
+            .collect()
+    }
+
+    /// Like `wrap`, also giving the line and the cell each row starts at.
+    pub fn wrap_indexed(&self, width: usize, no_wrap: bool) -> Vec<(usize, usize, Line)> {
+        let width = width.max(1);
+        let mut rows = Vec::new();
+        for (idx, line) in self.lines.iter().enumerate() {
+            let mut row = Line::default();
+            let mut start = 0;
+            let mut col = 0;
+            for (cell_idx, cell) in line.cells.iter().enumerate() {
+                if col + cell.width > width {
+                    if no_wrap {
+                        break;
+                    }
+                    if col == 0 {
+                        // Too wide for the screen at all; let it overflow.
+                        rows.push((idx, cell_idx, Line { cells: vec![cell.clone()] }));
+                        start = cell_idx + 1;
+                        continue;
+                    }
+                    rows.push((idx, start, std::mem::take(&mut row)));
+                    start = cell_idx;
+                    col = 0;
+                }
+                col += cell.width;
+                row.cells.push(cell.clone());
+            }
+            rows.push((idx, start, row));
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain_rows(rows: &[Line]) -> Vec<String> {
+        rows.iter().map(Line::to_plain).collect()
+    }
+
+    #[test]
+    fn parses_styles_and_ignores_other_sequences() {
+        let grid = Grid::parse("\x1b[31mre\x1b[0md\x1b]0;title\x07\n\x1b[2Kok");
+        assert_eq!(grid.text(), "red\nok");
+        assert_eq!(grid.lines[0].to_ansi(), "\x1b[31mre\x1b[0md");
+    }
+
+    #[test]
+    fn carriage_return_and_backspace_overwrite() {
+        let grid = Grid::parse("progress 10%\rprogress 100%\nab\x08X\nabc\r漢");
+        assert_eq!(grid.text(), "progress 100%\naX\n漢c");
+        let grid = Grid::parse("漢字\rx");
+        assert_eq!(grid.text(), "x 字");
+    }
+
+    #[test]
+    fn tabs_expand_to_tab_stops() {
+        assert_eq!(Grid::parse("a\tb\nabcdefgh\tc").text(), "a       b\nabcdefgh        c");
+        assert_eq!(Grid::parse_with_tab_width("ab\tc\t", 4).text(), "ab  c");
+        assert_eq!(Grid::parse_with_tab_width("abcdef\r\tX", 4).text(), "abcdXf");
+    }
+
+    #[test]
+    fn keeps_grapheme_clusters_in_one_cell() {
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        let flag = "\u{1f1fa}\u{1f1f8}";
+        let heart = "\u{2764}\u{fe0f}";
+        let grid = Grid::parse(&format!("a{family}{flag}{heart}e\u{301}"));
+        let cells: Vec<(&str, usize)> = grid.lines[0]
+            .cells
+            .iter()
+            .map(|cell| (cell.text.as_str(), cell.width))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![("a", 1), (family, 2), (flag, 2), (heart, 2), ("e\u{301}", 1)]
+        );
+        assert_eq!(
+            plain_rows(&grid.wrap(4, false)),
+            vec![format!("a{family}"), format!("{flag}{heart}"), "e\u{301}".to_string()]
+        );
+        assert_eq!(plain_rows(&grid.wrap(4, true)), vec![format!("a{family}")]);
+    }
+
+    #[test]
+    fn wraps_and_truncates_by_width() {
+        let grid = Grid::parse("ab漢字e\u{301}f");
+        assert_eq!(plain_rows(&grid.wrap(3, false)), vec!["ab", "漢", "字e\u{301}", "f"]);
+        assert_eq!(plain_rows(&grid.wrap(3, true)), vec!["ab"]);
+    }
+}
@@ -0,0 +1,230 @@
+use unicode_width::UnicodeWidthStr;
+
+use crate::ansi::Style;
+use crate::config::{ColorMode, Config, DifferencesMode};
+use crate::grid::{Grid, Line};
+use crate::input::Action;
+use crate::render::TerminalSize;
+
+/// What `?` shows: every action with its keys, then the settings in
+/// effect. `schedule` is the focused pane's schedule, as in its header, and
+/// `differences` the diff mode as toggled while running.
+pub fn help_lines(
+    config: &Config,
+    schedule: &str,
+    differences: Option<DifferencesMode>,
+) -> Vec<Line> {
+    let keys: Vec<(Action, String)> = Action::ALL
+        .into_iter()
+        .map(|action| match config.keymap.keys(action) {
+            keys if keys.is_empty() => (action, "(none)".to_string()),
+            keys => (action, keys),
+        })
+        .collect();
+    let settings = [
+        ("interval", schedule.to_string()),
+        (
+            "differences",
+            match differences {
+                None => "off",
+                Some(DifferencesMode::Changes) => "changes",
+                Some(DifferencesMode::Permanent) => "permanent",
+            }
+            .to_string(),
+        ),
+        (
+            "color",
+            match config.color {
+                ColorMode::Auto => "auto",
+                ColorMode::Always => "always",
+                ColorMode::Never => "never",
+            }
+            .to_string(),
+        ),
+        ("exit", exit_conditions(config)),
+    ];
+    let width = keys
+        .iter()
+        .map(|(_, keys)| UnicodeWidthStr::width(keys.as_str()))
+        .chain(settings.iter().map(|(name, _)| name.len()))
+        .max()
+        .unwrap_or(0);
+
+    let bold = Style {
+        bold: true,
+        ..Style::default()
+    };
+    let mut lines = vec![Line::styled("Keys", bold)];
+    for (action, keys) in &keys {
+        lines.push(entry(keys, action.description(), width));
+    }
+    lines.push(Line::default());
+    lines.push(Line::styled("Settings", bold));
+    for (name, value) in &settings {
+        lines.push(entry(name, value, width));
+    }
+    lines.push(Line::default());
+    lines.push(Line::styled(
+        "Press any key to close",
+        Style {
+            dim: true,
+            ..Style::default()
+        },
+    ));
+    lines
+}
+
+fn entry(name: &str, value: &str, width: usize) -> Line {
+    let mut line = Line::plain(&format!("  {name}"));
+    line.pad(width + 4);
+    line.extend(Line::plain(value));
+    line
+}
+
+fn exit_conditions(config: &Config) -> String {
+    let mut conditions = Vec::new();
+    if config.errexit {
+        conditions.push("on failure".to_string());
+    }
+    if config.chgexit {
+        conditions.push("on change".to_string());
+    }
+    if let Some(cycles) = config.equexit {
+        conditions.push(format!("after {cycles} unchanged runs"));
+    }
+    if conditions.is_empty() {
+        return "never".to_string();
+    }
+    conditions.join(", ")
+}
+
+/// Draws `lines` in a box in the middle of `frame`, which stays visible
+/// around it. Lines that do not fit are cut off.
+pub fn overlay(frame: &mut Vec<Line>, lines: &[Line], size: TerminalSize) {
+    let (columns, rows) = (size.columns as usize, size.rows as usize);
+    if columns < 4 || rows < 3 {
+        return;
+    }
+    let inner = lines
+        .iter()
+        .map(Line::width)
+        .max()
+        .unwrap_or(0)
+        .min(columns - 4);
+    let height = (lines.len() + 2).min(rows);
+
+    let mut top = Line::plain("\u{250c}\u{2500} Help ");
+    while top.width() < inner + 3 {
+        top.extend(Line::plain("\u{2500}"));
+    }
+    let mut boxed = vec![fit(top, inner + 3)];
+    boxed.last_mut().unwrap().extend(Line::plain("\u{2510}"));
+    for line in &lines[..height - 2] {
+        let mut row = Line::plain("\u{2502} ");
+        row.extend(fit(line.clone(), inner));
+        row.pad(inner + 2);
+        row.extend(Line::plain(" \u{2502}"));
+        boxed.push(row);
+    }
+    boxed.push(Line::plain(&format!(
+        "\u{2514}{}\u{2518}",
+        "\u{2500}".repeat(inner + 2)
+    )));
+
+    let left = (columns - (inner + 4)) / 2;
+    let first = (rows - height) / 2;
+    frame.resize(frame.len().max(rows), Line::default());
+    for (offset, row) in boxed.into_iter().enumerate() {
+        let target = &mut frame[first + offset];
+        *target = splice(target, left, row);
+    }
+}
+
+/// Cuts `line` down to `width` columns.
+fn fit(line: Line, width: usize) -> Line {
+    Grid { lines: vec![line] }
+        .wrap(width, true)
+        .into_iter()
+        .next()
+        .unwrap_or_default()
+}
+
+/// `row` with `piece` drawn over it from column `at`. Wide characters cut
+/// in half by the piece are blanked.
+fn splice(row: &Line, at: usize, piece: Line) -> Line {
+    let end = at + piece.width();
+    let mut spliced = Line::default();
+    let mut after = Vec::new();
+    let mut after_start = None;
+    let mut col = 0;
+    for cell in &row.cells {
+        if col + cell.width <= at {
+            spliced.cells.push(cell.clone());
+        } else if col >= end {
+            after_start.get_or_insert(col);
+            after.push(cell.clone());
+        }
+        col += cell.width;
+    }
+    spliced.pad(at);
+    spliced.extend(piece);
+    if let Some(start) = after_start {
+        spliced.pad(start);
+        spliced.cells.extend(after);
+    }
+    spliced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+
+    fn config(args: &[&str]) -> Config {
+        let cli = Cli::parse_from_iter(args.iter().copied()).unwrap();
+        Config::from_cli_with(cli, &|_| None).unwrap()
+    }
+
+    #[test]
+    fn lists_every_action_and_the_settings() {
+        let config = config(&["watch", "-d", "--chgexit", "--equexit", "3", "date"]);
+        let text: Vec<String> = help_lines(&config, "Every 2.0s", config.differences)
+            .iter()
+            .map(Line::to_plain)
+            .collect();
+        for action in Action::ALL {
+            assert!(
+                text.iter().any(|line| line.contains(action.description())),
+                "{action:?} is missing"
+            );
+        }
+        assert!(text.contains(&"  ?, h          show this help".to_string()));
+        assert!(text.contains(&"  interval      Every 2.0s".to_string()));
+        assert!(text.contains(&"  differences   changes".to_string()));
+        assert!(text.contains(&"  exit          on change, after 3 unchanged runs".to_string()));
+    }
+
+    #[test]
+    fn draws_box_over_the_frame() {
+        let mut frame: Vec<Line> = ["0123456789", "abcdefghij", "ABCDEFGHIJ", "xyz"]
+            .iter()
+            .map(|text| Line::plain(text))
+            .collect();
+        let size = TerminalSize {
+            columns: 10,
+            rows: 5,
+        };
+        overlay(&mut frame, &[Line::plain("hi")], size);
+        let text: Vec<String> = frame.iter().map(Line::to_plain).collect();
+        assert_eq!(
+            text,
+            vec!["0123456789", "ab┌─ He┐ij", "AB│ hi │IJ", "xy└────┘", ""]
+        );
+    }
+
+    #[test]
+    fn splice_blanks_split_wide_characters() {
+        let row = Line::plain("漢字漢字");
+        assert_eq!(splice(&row, 1, Line::plain("ab")).to_plain(), " ab 漢字");
+    }
+}
@@ -0,0 +1,307 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::exec::{build_command, ExecError, ExecOptions};
+
+pub const DEFAULT_HOOK_GAP: Duration = Duration::from_secs(1);
+
+/// How long watch waits for running hooks when it exits right after firing
+/// them, as with `--chgexit`.
+pub const HOOK_EXIT_WAIT: Duration = Duration::from_secs(2);
+
+/// Outputs larger than this are only passed through the temp files, keeping
+/// the environment within platform limits.
+const MAX_ENV_OUTPUT: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    /// The output differs from the previous run.
+    Change,
+    /// The command started failing.
+    Fail,
+    /// The command succeeded again after failing.
+    Recover,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::Change => "change",
+            HookEvent::Fail => "fail",
+            HookEvent::Recover => "recover",
+        }
+    }
+
+    /// Events raised by a run, given whether the previous run succeeded
+    /// (`None` before the first run).
+    pub fn for_run(changed: bool, previous: Option<bool>, success: bool) -> Vec<HookEvent> {
+        let mut events = Vec::new();
+        if changed {
+            events.push(HookEvent::Change);
+        }
+        match (previous, success) {
+            (Some(true) | None, false) => events.push(HookEvent::Fail),
+            (Some(false), true) => events.push(HookEvent::Recover),
+            _ => {}
+        }
+        events
+    }
+}
+
+/// What a hook gets to see about the run that raised it.
+pub struct HookContext<'a> {
+    pub command: &'a [String],
+    pub exit_code: Option<i32>,
+    pub old_output: &'a str,
+    pub new_output: &'a str,
+}
+
+struct Running {
+    child: Child,
+    event: HookEvent,
+    files: Vec<PathBuf>,
+}
+
+/// Runs the `--on-change`, `--on-fail` and `--on-recover` commands in the
+/// background. A hook is skipped while its previous invocation is still
+/// running or fired less than `gap` ago.
+pub struct Hooks {
+    commands: Vec<(HookEvent, String)>,
+    options: ExecOptions,
+    gap: Duration,
+    last_fired: Vec<(HookEvent, Instant)>,
+    running: Vec<Running>,
+    counter: u64,
+}
+
+impl Hooks {
+    pub fn new(commands: Vec<(HookEvent, String)>, options: ExecOptions, gap: Duration) -> Self {
+        Self {
+            commands,
+            options,
+            gap,
+            last_fired: Vec::new(),
+            running: Vec::new(),
+            counter: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Starts the hook for `event`, if one is configured and not held back.
+    pub fn fire(&mut self, event: HookEvent, context: &HookContext<'_>) -> Result<(), ExecError> {
+        self.reap();
+        let Some(hook) = self
+            .commands
+            .iter()
+            .find(|(kind, _)| *kind == event)
+            .map(|(_, hook)| hook.clone())
+        else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let recent = self
+            .last_fired
+            .iter()
+            .any(|(kind, at)| *kind == event && now.duration_since(*at) < self.gap);
+        if recent || self.running.iter().any(|running| running.event == event) {
+            return Ok(());
+        }
+        self.last_fired.retain(|(kind, _)| *kind != event);
+        self.last_fired.push((event, now));
+
+        self.counter += 1;
+        let stem = format!("watch-hook-{}-{}", std::process::id(), self.counter);
+        let old_file = std::env::temp_dir().join(format!("{stem}-old.txt"));
+        let new_file = std::env::temp_dir().join(format!("{stem}-new.txt"));
+        let files = vec![old_file.clone(), new_file.clone()];
+        let written = write_private(&old_file, context.old_output)
+            .and_then(|()| write_private(&new_file, context.new_output));
+        if let Err(err) = written {
+            remove_files(&files);
+            return Err(ExecError::SpawnFailed(err.to_string()));
+        }
+
+        let mut cmd = build_command(&[hook], &self.options)?;
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .env("WATCH_EVENT", event.name())
+            .env("WATCH_COMMAND", context.command.join(" "))
+            .env("WATCH_OLD_OUTPUT_FILE", &old_file)
+            .env("WATCH_NEW_OUTPUT_FILE", &new_file);
+        match context.exit_code {
+            Some(code) => cmd.env("WATCH_EXIT", code.to_string()),
+            None => cmd.env_remove("WATCH_EXIT"),
+        };
+        for (key, output) in [
+            ("WATCH_OLD_OUTPUT", context.old_output),
+            ("WATCH_NEW_OUTPUT", context.new_output),
+        ] {
+            if output.len() <= MAX_ENV_OUTPUT && !output.contains('\0') {
+                cmd.env(key, output);
+            } else {
+                cmd.env_remove(key);
+            }
+        }
+
+        match cmd.spawn() {
+            Ok(child) => {
+                self.running.push(Running {
+                    child,
+                    event,
+                    files,
+                });
+                Ok(())
+            }
+            Err(err) => {
+                remove_files(&files);
+                Err(ExecError::SpawnFailed(err.to_string()))
+            }
+        }
+    }
+
+    /// Collects finished hooks and removes their temp files.
+    pub fn reap(&mut self) {
+        self.running.retain_mut(|running| match running.child.try_wait() {
+            Ok(None) => true,
+            Ok(Some(_)) | Err(_) => {
+                remove_files(&running.files);
+                false
+            }
+        });
+    }
+
+    /// Gives running hooks up to `timeout` to finish, collecting the ones
+    /// that do.
+    pub fn finish(&mut self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        self.reap();
+        while !self.running.is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+            self.reap();
+        }
+    }
+}
+
+/// Hooks still running at exit are left to finish, and so are their temp
+/// files, which they may not have read yet.
+impl Drop for Hooks {
+    fn drop(&mut self) {
+        self.reap();
+    }
+}
+
+/// Creates `path` readable only by the user, failing if it already exists
+/// rather than following a link someone else left there.
+fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+fn remove_files(files: &[PathBuf]) {
+    for file in files {
+        let _ = fs::remove_file(file);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fail_and_recover_fire_on_transitions() {
+        assert_eq!(HookEvent::for_run(false, None, false), vec![HookEvent::Fail]);
+        assert_eq!(HookEvent::for_run(false, Some(false), false), vec![]);
+        assert_eq!(
+            HookEvent::for_run(true, Some(false), true),
+            vec![HookEvent::Change, HookEvent::Recover]
+        );
+        assert_eq!(HookEvent::for_run(false, Some(true), true), vec![]);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn hook_sees_outputs_and_is_rate_limited() {
+        use crate::exec::Shell;
+
+        let target = std::env::temp_dir().join(format!("watch-hook-test-{}", std::process::id()));
+        let options = ExecOptions {
+            shell: Some(Shell::new("/bin/sh", &[])),
+            ..ExecOptions::default()
+        };
+        let hook = format!(
+            "printf '%s>%s:%s' \"$WATCH_OLD_OUTPUT\" \"$(cat \"$WATCH_NEW_OUTPUT_FILE\")\" \"$WATCH_EVENT\" >> '{}'",
+            target.display()
+        );
+        let mut hooks = Hooks::new(
+            vec![(HookEvent::Change, hook)],
+            options,
+            Duration::from_secs(60),
+        );
+        let context = HookContext {
+            command: &["date".to_string()],
+            exit_code: Some(0),
+            old_output: "a",
+            new_output: "b",
+        };
+        hooks.fire(HookEvent::Change, &context).unwrap();
+        hooks.fire(HookEvent::Change, &context).unwrap();
+        hooks.fire(HookEvent::Fail, &context).unwrap();
+        hooks.finish(Duration::from_secs(10));
+        assert!(hooks.running.is_empty());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "a>b:change");
+        let _ = fs::remove_file(&target);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn drop_keeps_temp_files_of_running_hooks() {
+        use crate::exec::Shell;
+
+        let options = ExecOptions {
+            shell: Some(Shell::new("/bin/sh", &[])),
+            ..ExecOptions::default()
+        };
+        let mut hooks = Hooks::new(
+            vec![(HookEvent::Fail, "sleep 5".to_string())],
+            options,
+            Duration::ZERO,
+        );
+        let context = HookContext {
+            command: &["false".to_string()],
+            exit_code: Some(1),
+            old_output: "",
+            new_output: "",
+        };
+        hooks.fire(HookEvent::Fail, &context).unwrap();
+        let files = hooks.running[0].files.clone();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&files[0]).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        drop(hooks);
+        assert!(files.iter().all(|file| file.exists()));
+        remove_files(&files);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn temp_file_is_not_written_through_an_existing_path() {
+        let path = std::env::temp_dir().join(format!("watch-hook-exists-{}", std::process::id()));
+        fs::write(&path, "theirs").unwrap();
+        assert!(write_private(&path, "ours").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "theirs");
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,26 +1,152 @@
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Action {
     Quit,
     Screenshot,
     Trigger,
+    IncreaseInterval,
+    DecreaseInterval,
+    Pause,
+    ToggleDiff,
+    Scroll(Scroll),
+    FocusNext,
+    Zoom,
+    Search,
+    NextMatch,
+    PrevMatch,
+    ClearSearch,
+    Filter,
+    Help,
 }
 
-pub fn action_from_event(event: Event) -> Option<Action> {
-    match event {
-        Event::Key(key) => action_from_key(key),
-        _ => None,
+impl Action {
+    /// Every action, in the order the help lists them.
+    pub const ALL: [Action; 21] = [
+        Action::Quit,
+        Action::Trigger,
+        Action::Screenshot,
+        Action::IncreaseInterval,
+        Action::DecreaseInterval,
+        Action::Pause,
+        Action::ToggleDiff,
+        Action::Scroll(Scroll::Up),
+        Action::Scroll(Scroll::Down),
+        Action::Scroll(Scroll::PageUp),
+        Action::Scroll(Scroll::PageDown),
+        Action::Scroll(Scroll::Top),
+        Action::Scroll(Scroll::Bottom),
+        Action::FocusNext,
+        Action::Zoom,
+        Action::Search,
+        Action::NextMatch,
+        Action::PrevMatch,
+        Action::ClearSearch,
+        Action::Filter,
+        Action::Help,
+    ];
+
+    /// The name used for the action in the `[keys]` config table.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Trigger => "rerun",
+            Action::Screenshot => "screenshot",
+            Action::IncreaseInterval => "increase-interval",
+            Action::DecreaseInterval => "decrease-interval",
+            Action::Pause => "pause",
+            Action::ToggleDiff => "toggle-diff",
+            Action::Scroll(Scroll::Up) => "scroll-up",
+            Action::Scroll(Scroll::Down) => "scroll-down",
+            Action::Scroll(Scroll::PageUp) => "page-up",
+            Action::Scroll(Scroll::PageDown) => "page-down",
+            Action::Scroll(Scroll::Top) => "top",
+            Action::Scroll(Scroll::Bottom) => "bottom",
+            Action::FocusNext => "focus-next",
+            Action::Zoom => "zoom",
+            Action::Search => "search",
+            Action::NextMatch => "next-match",
+            Action::PrevMatch => "prev-match",
+            Action::ClearSearch => "clear-search",
+            Action::Filter => "filter",
+            Action::Help => "help",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Trigger => "run the command now",
+            Action::Screenshot => "save a screenshot",
+            Action::IncreaseInterval => "double the interval",
+            Action::DecreaseInterval => "halve the interval",
+            Action::Pause => "pause or resume runs",
+            Action::ToggleDiff => "toggle highlighting changes",
+            Action::Scroll(Scroll::Up) => "scroll up",
+            Action::Scroll(Scroll::Down) => "scroll down",
+            Action::Scroll(Scroll::PageUp) => "page up",
+            Action::Scroll(Scroll::PageDown) => "page down",
+            Action::Scroll(Scroll::Top) => "go to the top",
+            Action::Scroll(Scroll::Bottom) => "go to the bottom",
+            Action::FocusNext => "focus the next pane",
+            Action::Zoom => "zoom the focused pane",
+            Action::Search => "search the output",
+            Action::NextMatch => "next match",
+            Action::PrevMatch => "previous match",
+            Action::ClearSearch => "clear the search",
+            Action::Filter => "filter the output",
+            Action::Help => "show this help",
+        }
     }
 }
 
-fn action_from_key(key: KeyEvent) -> Option<Action> {
+pub fn parse_action(name: &str) -> Result<Action, String> {
+    Action::ALL
+        .into_iter()
+        .find(|action| action.name() == name)
+        .ok_or_else(|| format!("unknown action '{name}'"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scroll {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+}
+
+/// What a key did to the text of a prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKey {
+    Edited,
+    Submit,
+    Cancel,
+    Ignored,
+}
+
+/// Applies `key` to the prompt `text`. Backspace on an empty prompt, Esc
+/// and Ctrl-C close it; Ctrl-U clears it.
+pub fn edit_prompt(text: &mut String, key: KeyEvent) -> PromptKey {
     match (key.code, key.modifiers) {
-        (KeyCode::Char('q'), _) | (KeyCode::Char('Q'), _) => Some(Action::Quit),
-        (KeyCode::Char('c'), KeyModifiers::CONTROL) => Some(Action::Quit),
-        (KeyCode::Char('s'), _) | (KeyCode::Char('S'), _) => Some(Action::Screenshot),
-        (KeyCode::Char(' '), _) => Some(Action::Trigger),
-        _ => None,
+        (KeyCode::Enter, _) => PromptKey::Submit,
+        (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => PromptKey::Cancel,
+        (KeyCode::Backspace, _) => match text.pop() {
+            Some(_) => PromptKey::Edited,
+            None => PromptKey::Cancel,
+        },
+        (KeyCode::Char('u'), KeyModifiers::CONTROL) => {
+            text.clear();
+            PromptKey::Edited
+        }
+        (KeyCode::Char(ch), modifiers)
+            if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+        {
+            text.push(ch);
+            PromptKey::Edited
+        }
+        _ => PromptKey::Ignored,
     }
 }
 
@@ -29,30 +155,25 @@ mod tests {
     use super::*;
 
     #[test]
-    fn maps_quit_keys() {
-        assert_eq!(
-            action_from_event(Event::Key(KeyEvent::from(KeyCode::Char('q')))),
-            Some(Action::Quit)
-        );
-        assert_eq!(
-            action_from_event(Event::Key(KeyEvent::from(KeyCode::Char('Q')))),
-            Some(Action::Quit)
-        );
-    }
-
-    #[test]
-    fn maps_trigger_key() {
-        assert_eq!(
-            action_from_event(Event::Key(KeyEvent::from(KeyCode::Char(' ')))),
-            Some(Action::Trigger)
-        );
+    fn edits_prompt_text() {
+        let mut text = String::new();
+        let key = |code| KeyEvent::from(code);
+        assert_eq!(edit_prompt(&mut text, key(KeyCode::Char('a'))), PromptKey::Edited);
+        assert_eq!(edit_prompt(&mut text, key(KeyCode::Char('B'))), PromptKey::Edited);
+        assert_eq!(text, "aB");
+        assert_eq!(edit_prompt(&mut text, key(KeyCode::Backspace)), PromptKey::Edited);
+        assert_eq!(edit_prompt(&mut text, key(KeyCode::Enter)), PromptKey::Submit);
+        assert_eq!(text, "a");
+        let ctrl_u = KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(edit_prompt(&mut text, ctrl_u), PromptKey::Edited);
+        assert_eq!(edit_prompt(&mut text, key(KeyCode::Backspace)), PromptKey::Cancel);
     }
 
     #[test]
-    fn maps_screenshot_key() {
-        assert_eq!(
-            action_from_event(Event::Key(KeyEvent::from(KeyCode::Char('s')))),
-            Some(Action::Screenshot)
-        );
+    fn action_names_round_trip() {
+        for action in Action::ALL {
+            assert_eq!(parse_action(action.name()).unwrap(), action);
+        }
+        assert!(parse_action("explode").is_err());
     }
 }
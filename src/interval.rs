@@ -8,6 +8,8 @@ pub const MAX_INTERVAL_SECS: f64 = 2_678_400.0;
 pub enum IntervalParseError {
     Empty,
     NotANumber,
+    UnknownUnit(String),
+    MissingUnit(String),
 }
 
 impl std::fmt::Display for IntervalParseError {
@@ -15,6 +17,14 @@ impl std::fmt::Display for IntervalParseError {
         match self {
             IntervalParseError::Empty => write!(f, "interval is empty"),
             IntervalParseError::NotANumber => write!(f, "interval is not a number"),
+            IntervalParseError::UnknownUnit(unit) => write!(
+                f,
+                "unknown interval unit '{unit}' (expected ms, s, m, h or d)"
+            ),
+            IntervalParseError::MissingUnit(number) => write!(
+                f,
+                "'{number}' needs a unit when other parts have one, e.g. '{number}s'"
+            ),
         }
     }
 }
@@ -22,24 +32,101 @@ impl std::fmt::Display for IntervalParseError {
 impl std::error::Error for IntervalParseError {}
 
 pub fn parse_interval(input: &str) -> Result<Duration, IntervalParseError> {
+    let secs = parse_secs(input)?;
+    Ok(Duration::from_secs_f64(secs.clamp(MIN_INTERVAL_SECS, MAX_INTERVAL_SECS)))
+}
+
+/// Parses the same syntax as `parse_interval` without clamping to the
+/// refresh interval limits; negative values become zero.
+pub fn parse_duration(input: &str) -> Result<Duration, IntervalParseError> {
+    let secs = parse_secs(input)?;
+    Ok(Duration::from_secs_f64(secs.clamp(0.0, MAX_INTERVAL_SECS)))
+}
+
+fn parse_secs(input: &str) -> Result<f64, IntervalParseError> {
     let trimmed = input.trim();
     if trimmed.is_empty() {
         return Err(IntervalParseError::Empty);
     }
 
     let normalized = trimmed.replace(',', ".");
-    let mut secs: f64 = normalized.parse().map_err(|_| IntervalParseError::NotANumber)?;
+    let secs = match normalized.parse::<f64>() {
+        Ok(secs) => secs,
+        Err(_) => parse_with_units(&normalized)?,
+    };
     if secs.is_nan() || secs.is_infinite() {
         return Err(IntervalParseError::NotANumber);
     }
+    Ok(secs)
+}
+
+fn parse_with_units(input: &str) -> Result<f64, IntervalParseError> {
+    let mut total = 0.0;
+    let mut rest = input;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+            .unwrap_or(rest.len());
+        if number_len == 0 {
+            return Err(IntervalParseError::NotANumber);
+        }
+        let value: f64 = rest[..number_len]
+            .parse()
+            .map_err(|_| IntervalParseError::NotANumber)?;
+        rest = &rest[number_len..];
+
+        let unit_len = rest
+            .find(|ch: char| ch.is_ascii_digit() || ch == '.')
+            .unwrap_or(rest.len());
+        let unit = &rest[..unit_len];
+        if unit.is_empty() {
+            return Err(IntervalParseError::MissingUnit(
+                input[input.len() - number_len..].to_string(),
+            ));
+        }
+        let scale = match unit.to_ascii_lowercase().as_str() {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3_600.0,
+            "d" => 86_400.0,
+            _ => return Err(IntervalParseError::UnknownUnit(unit.to_string())),
+        };
+        total += value * scale;
+        rest = &rest[unit_len..];
+    }
+    Ok(total)
+}
 
-    if secs < MIN_INTERVAL_SECS {
-        secs = MIN_INTERVAL_SECS;
-    } else if secs > MAX_INTERVAL_SECS {
-        secs = MAX_INTERVAL_SECS;
+/// Formats an interval the way it can be passed back to `parse_interval`,
+/// e.g. `2.0s`, `250ms` or `1h30m`.
+pub fn format_interval(interval: Duration) -> String {
+    let millis = interval.as_millis();
+    if millis < 60_000 {
+        if millis.is_multiple_of(100) {
+            return format!("{:.1}s", interval.as_secs_f64());
+        }
+        if millis < 1_000 {
+            return format!("{millis}ms");
+        }
+        return format!("{:.3}s", interval.as_secs_f64());
     }
 
-    Ok(Duration::from_secs_f64(secs))
+    let mut out = String::new();
+    let mut secs = interval.as_secs();
+    for (unit, size) in [("d", 86_400), ("h", 3_600), ("m", 60)] {
+        if secs >= size {
+            out.push_str(&format!("{}{unit}", secs / size));
+            secs %= size;
+        }
+    }
+    let tenths = interval.subsec_millis() / 100;
+    if tenths > 0 {
+        out.push_str(&format!("{secs}.{tenths}s"));
+    } else if secs > 0 {
+        out.push_str(&format!("{secs}s"));
+    }
+    out
 }
 
 pub fn default_interval() -> Duration {
@@ -68,6 +155,46 @@ mod tests {
         assert_eq!(parsed, Duration::from_secs_f64(0.5));
     }
 
+    #[test]
+    fn parse_interval_accepts_units() {
+        assert_eq!(parse_interval("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_interval("1m30s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_interval("2h").unwrap(), Duration::from_secs(7_200));
+        assert_eq!(parse_interval("1d").unwrap(), Duration::from_secs(86_400));
+        assert_eq!(parse_interval("1,5m").unwrap(), Duration::from_secs(90));
+    }
+
+    #[test]
+    fn parse_duration_does_not_clamp_low() {
+        assert_eq!(parse_duration("20ms").unwrap(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn parse_interval_rejects_unknown_unit() {
+        assert_eq!(
+            parse_interval("5x").unwrap_err(),
+            IntervalParseError::UnknownUnit("x".to_string())
+        );
+        assert_eq!(parse_interval("ms").unwrap_err(), IntervalParseError::NotANumber);
+        assert_eq!(
+            parse_interval("1m30").unwrap_err(),
+            IntervalParseError::MissingUnit("30".to_string())
+        );
+        assert_eq!(
+            parse_interval("1m30").unwrap_err().to_string(),
+            "'30' needs a unit when other parts have one, e.g. '30s'"
+        );
+    }
+
+    #[test]
+    fn format_interval_humanizes() {
+        assert_eq!(format_interval(Duration::from_secs(2)), "2.0s");
+        assert_eq!(format_interval(Duration::from_millis(250)), "250ms");
+        assert_eq!(format_interval(Duration::from_secs(90)), "1m30s");
+        assert_eq!(format_interval(Duration::from_secs(5_400)), "1h30m");
+        assert_eq!(format_interval(Duration::from_secs(86_400)), "1d");
+    }
+
     #[test]
     fn parse_interval_rejects_empty() {
         assert_eq!(parse_interval("  ").unwrap_err(), IntervalParseError::Empty);
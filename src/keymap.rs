@@ -0,0 +1,444 @@
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::input::{Action, Scroll};
+
+/// A key and the modifiers held with it. For character keys Shift is part
+/// of the character, so `N` is Shift-n, and Ctrl-C is the same as Ctrl-c.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Char(ch) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                if modifiers.contains(KeyModifiers::CONTROL) {
+                    KeyCode::Char(ch.to_ascii_lowercase())
+                } else {
+                    code
+                }
+            }
+            KeyCode::BackTab => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                code
+            }
+            _ => code,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("Shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(ch) => write!(f, "{ch}"),
+            KeyCode::Enter => f.write_str("Enter"),
+            KeyCode::Esc => f.write_str("Esc"),
+            KeyCode::Tab => f.write_str("Tab"),
+            KeyCode::BackTab => f.write_str("Shift-Tab"),
+            KeyCode::Backspace => f.write_str("Backspace"),
+            KeyCode::Delete => f.write_str("Delete"),
+            KeyCode::Insert => f.write_str("Insert"),
+            KeyCode::Up => f.write_str("Up"),
+            KeyCode::Down => f.write_str("Down"),
+            KeyCode::Left => f.write_str("Left"),
+            KeyCode::Right => f.write_str("Right"),
+            KeyCode::PageUp => f.write_str("PageUp"),
+            KeyCode::PageDown => f.write_str("PageDown"),
+            KeyCode::Home => f.write_str("Home"),
+            KeyCode::End => f.write_str("End"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+/// Parses a key such as `q`, `Ctrl-s`, `Alt-Enter` or `PageDown`. Names
+/// and modifiers ignore case; single characters do not.
+pub fn parse_key(raw: &str) -> Result<KeyPress, String> {
+    let mut rest = raw.trim();
+    let mut modifiers = KeyModifiers::NONE;
+    loop {
+        let lower = rest.to_ascii_lowercase();
+        let (modifier, len) = if lower.starts_with("ctrl-") {
+            (KeyModifiers::CONTROL, 5)
+        } else if lower.starts_with("alt-") {
+            (KeyModifiers::ALT, 4)
+        } else if lower.starts_with("shift-") {
+            (KeyModifiers::SHIFT, 6)
+        } else {
+            break;
+        };
+        // `Ctrl--` is Ctrl and the minus key.
+        if rest.len() == len {
+            break;
+        }
+        modifiers |= modifier;
+        rest = &rest[len..];
+    }
+
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(ch), None) => KeyCode::Char(ch),
+        _ => match rest.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(format!("unknown key '{raw}'")),
+            },
+        },
+    };
+    let code = match code {
+        KeyCode::Char(ch) if modifiers.contains(KeyModifiers::SHIFT) && ch.is_lowercase() => {
+            KeyCode::Char(ch.to_ascii_uppercase())
+        }
+        code => code,
+    };
+    Ok(KeyPress::new(code, modifiers))
+}
+
+/// Parses keys pressed one after the other, separated by spaces, e.g.
+/// `g g` or `Ctrl-x s`.
+pub fn parse_sequence(raw: &str) -> Result<Vec<KeyPress>, String> {
+    let keys = raw
+        .split_whitespace()
+        .map(parse_key)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Err("empty key".to_string());
+    }
+    Ok(keys)
+}
+
+/// An action and the key sequences bound to it, as in the `[keys]` table.
+pub type KeyBinding = (Action, Vec<Vec<KeyPress>>);
+
+/// Which action each key, or sequence of keys, runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyPress>, Action)>,
+}
+
+/// What the keys pressed so far amount to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    Action(Action),
+    /// The start of a longer sequence.
+    Prefix,
+    Unbound,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = DEFAULT_KEYS
+            .iter()
+            .flat_map(|&(action, keys)| {
+                keys.iter()
+                    .map(move |key| (parse_sequence(key).expect("default keys parse"), action))
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// The default bindings, except that each action in `overrides` is
+    /// bound to the given keys instead; an empty list unbinds it. Fails when
+    /// a key runs two actions, or is the start of another action's
+    /// sequence.
+    pub fn new(overrides: &[KeyBinding]) -> Result<Self, String> {
+        let mut bindings: Vec<(Vec<KeyPress>, Action)> = Keymap::default()
+            .bindings
+            .into_iter()
+            .filter(|(_, action)| !overrides.iter().any(|(over, _)| over == action))
+            .collect();
+        for (action, sequences) in overrides {
+            for keys in sequences {
+                if !bindings.contains(&(keys.clone(), *action)) {
+                    bindings.push((keys.clone(), *action));
+                }
+            }
+        }
+
+        for (idx, (keys, action)) in bindings.iter().enumerate() {
+            for (other_keys, other) in &bindings[idx + 1..] {
+                let (short, long) = if keys.len() <= other_keys.len() {
+                    ((keys, action), (other_keys, other))
+                } else {
+                    ((other_keys, other), (keys, action))
+                };
+                if !long.0.starts_with(short.0) {
+                    continue;
+                }
+                if short.0.len() == long.0.len() {
+                    return Err(format!(
+                        "key '{}' is bound to both {} and {}",
+                        sequence_name(keys),
+                        action.name(),
+                        other.name()
+                    ));
+                }
+                return Err(format!(
+                    "key '{}' for {} is the start of '{}' for {}",
+                    sequence_name(short.0),
+                    short.1.name(),
+                    sequence_name(long.0),
+                    long.1.name()
+                ));
+            }
+        }
+        if !bindings.iter().any(|(_, action)| *action == Action::Quit) {
+            return Err("quit needs at least one key".to_string());
+        }
+        Ok(Self { bindings })
+    }
+
+    pub fn lookup(&self, keys: &[KeyPress]) -> Lookup {
+        let mut lookup = Lookup::Unbound;
+        for (bound, action) in &self.bindings {
+            if bound == keys {
+                return Lookup::Action(*action);
+            }
+            if bound.starts_with(keys) {
+                lookup = Lookup::Prefix;
+            }
+        }
+        lookup
+    }
+
+    /// The keys bound to `action`, for the help, e.g. `q, Q, Ctrl-c`.
+    pub fn keys(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(keys, _)| sequence_name(keys))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn sequence_name(keys: &[KeyPress]) -> String {
+    keys.iter()
+        .map(KeyPress::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+const DEFAULT_KEYS: &[(Action, &[&str])] = &[
+    (Action::Quit, &["q", "Q", "Ctrl-c"]),
+    (Action::Trigger, &["space"]),
+    (Action::Screenshot, &["s", "S"]),
+    (Action::IncreaseInterval, &["+", "=", "]"]),
+    (Action::DecreaseInterval, &["-", "["]),
+    (Action::Pause, &["p"]),
+    (Action::ToggleDiff, &["d"]),
+    (Action::Scroll(Scroll::Up), &["Up", "k"]),
+    (Action::Scroll(Scroll::Down), &["Down", "j"]),
+    (Action::Scroll(Scroll::PageUp), &["PageUp"]),
+    (Action::Scroll(Scroll::PageDown), &["PageDown"]),
+    (Action::Scroll(Scroll::Top), &["Home", "g"]),
+    (Action::Scroll(Scroll::Bottom), &["End", "G"]),
+    (Action::FocusNext, &["Tab"]),
+    (Action::Zoom, &["z", "Z"]),
+    (Action::Search, &["/"]),
+    (Action::NextMatch, &["n"]),
+    (Action::PrevMatch, &["N"]),
+    (Action::ClearSearch, &["Esc"]),
+    (Action::Filter, &["&"]),
+    (Action::Help, &["?", "h"]),
+];
+
+/// Turns key presses into actions, waiting for the rest of a sequence
+/// when a key starts one.
+#[derive(Debug, Clone)]
+pub struct KeyReader {
+    keymap: Keymap,
+    pending: Vec<KeyPress>,
+}
+
+impl KeyReader {
+    pub fn new(keymap: Keymap) -> Self {
+        Self {
+            keymap,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn feed(&mut self, key: KeyEvent) -> Option<Action> {
+        self.pending.push(KeyPress::from(key));
+        match self.keymap.lookup(&self.pending) {
+            Lookup::Action(action) => {
+                self.pending.clear();
+                Some(action)
+            }
+            Lookup::Prefix => None,
+            // A key that breaks off a sequence counts on its own.
+            Lookup::Unbound if self.pending.len() > 1 => {
+                self.pending.clear();
+                self.feed(key)
+            }
+            Lookup::Unbound => {
+                self.pending.clear();
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(keymap: &Keymap, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        KeyReader::new(keymap.clone()).feed(KeyEvent::new(code, modifiers))
+    }
+
+    fn key(code: KeyCode) -> Option<Action> {
+        action(&Keymap::default(), code, KeyModifiers::NONE)
+    }
+
+    fn binding(action: Action, keys: &[&str]) -> KeyBinding {
+        let keys = keys.iter().map(|raw| parse_sequence(raw).unwrap()).collect();
+        (action, keys)
+    }
+
+    #[test]
+    fn maps_quit_keys() {
+        assert_eq!(key(KeyCode::Char('q')), Some(Action::Quit));
+        assert_eq!(key(KeyCode::Char('Q')), Some(Action::Quit));
+        assert_eq!(
+            action(&Keymap::default(), KeyCode::Char('c'), KeyModifiers::CONTROL),
+            Some(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn maps_default_keys() {
+        assert_eq!(key(KeyCode::Char(' ')), Some(Action::Trigger));
+        assert_eq!(key(KeyCode::Char('s')), Some(Action::Screenshot));
+        assert_eq!(key(KeyCode::Char('+')), Some(Action::IncreaseInterval));
+        assert_eq!(key(KeyCode::Char('[')), Some(Action::DecreaseInterval));
+        assert_eq!(key(KeyCode::Tab), Some(Action::FocusNext));
+        assert_eq!(key(KeyCode::PageDown), Some(Action::Scroll(Scroll::PageDown)));
+        assert_eq!(key(KeyCode::Char('z')), Some(Action::Zoom));
+        assert_eq!(key(KeyCode::Char('/')), Some(Action::Search));
+        assert_eq!(
+            action(&Keymap::default(), KeyCode::Char('N'), KeyModifiers::SHIFT),
+            Some(Action::PrevMatch)
+        );
+        assert_eq!(key(KeyCode::Char('&')), Some(Action::Filter));
+        assert_eq!(key(KeyCode::Char('x')), None);
+    }
+
+    #[test]
+    fn every_action_has_a_default_key() {
+        let keymap = Keymap::default();
+        for action in Action::ALL {
+            assert!(!keymap.keys(action).is_empty(), "{action:?} has no key");
+        }
+    }
+
+    #[test]
+    fn parses_keys_with_modifiers() {
+        let ctrl_s = KeyPress::new(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert_eq!(parse_key("ctrl-S").unwrap(), ctrl_s);
+        assert_eq!(parse_key("Shift-n").unwrap(), parse_key("N").unwrap());
+        assert_eq!(parse_key("Alt-Enter").unwrap().to_string(), "Alt-Enter");
+        assert_eq!(parse_key("Ctrl--").unwrap().to_string(), "Ctrl--");
+        assert_eq!(parse_key("shift-tab").unwrap().code, KeyCode::BackTab);
+        assert_eq!(parse_key("f5").unwrap().code, KeyCode::F(5));
+        assert!(parse_key("Hyper-x").is_err());
+        assert!(parse_sequence(" ").is_err());
+    }
+
+    #[test]
+    fn overrides_replace_default_keys() {
+        let keymap = Keymap::new(&[
+            binding(Action::Quit, &["Ctrl-q"]),
+            binding(Action::Trigger, &["r"]),
+            binding(Action::Screenshot, &[]),
+        ])
+        .unwrap();
+        assert_eq!(key_in(&keymap, KeyCode::Char('q')), None);
+        assert_eq!(key_in(&keymap, KeyCode::Char(' ')), None);
+        assert_eq!(key_in(&keymap, KeyCode::Char('r')), Some(Action::Trigger));
+        assert_eq!(key_in(&keymap, KeyCode::Char('s')), None);
+        assert_eq!(keymap.keys(Action::Quit), "Ctrl-q");
+    }
+
+    fn key_in(keymap: &Keymap, code: KeyCode) -> Option<Action> {
+        action(keymap, code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn reads_key_sequences() {
+        let keymap = Keymap::new(&[
+            binding(Action::Scroll(Scroll::Top), &["g g"]),
+            binding(Action::Screenshot, &["Ctrl-x s"]),
+        ])
+        .unwrap();
+        let mut reader = KeyReader::new(keymap);
+        let press = |code| KeyEvent::from(code);
+        assert_eq!(reader.feed(press(KeyCode::Char('g'))), None);
+        assert_eq!(
+            reader.feed(press(KeyCode::Char('g'))),
+            Some(Action::Scroll(Scroll::Top))
+        );
+        // A key that does not continue the sequence runs its own action.
+        assert_eq!(reader.feed(press(KeyCode::Char('g'))), None);
+        assert_eq!(reader.feed(press(KeyCode::Char('q'))), Some(Action::Quit));
+        assert_eq!(
+            reader.feed(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL)),
+            None
+        );
+        assert_eq!(reader.feed(press(KeyCode::Char('s'))), Some(Action::Screenshot));
+    }
+
+    #[test]
+    fn rejects_conflicting_keys() {
+        let err = Keymap::new(&[binding(Action::Quit, &["j"])]).unwrap_err();
+        assert_eq!(err, "key 'j' is bound to both scroll-down and quit");
+        let err = Keymap::new(&[binding(Action::Zoom, &["g z"])]).unwrap_err();
+        assert_eq!(err, "key 'g' for top is the start of 'g z' for zoom");
+        let err = Keymap::new(&[binding(Action::Quit, &[])]).unwrap_err();
+        assert_eq!(err, "quit needs at least one key");
+    }
+}
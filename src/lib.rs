@@ -1,12 +1,29 @@
+pub mod ansi;
 pub mod cli;
 pub mod config;
+pub mod cron;
 pub mod app;
+pub mod condition;
 pub mod diff;
 pub mod exec;
+pub mod filter;
+pub mod follow;
+pub mod grid;
+pub mod help;
+pub mod hooks;
 pub mod input;
 pub mod interval;
+pub mod keymap;
+pub mod notify;
+pub mod pane;
+pub mod pattern;
 pub mod render;
+pub mod schedule;
+pub mod screen;
 pub mod screenshot;
+pub mod search;
+pub mod settings;
 pub mod terminal;
+pub mod watcher;
 
 pub use crate::config::{ColorMode, Config, DifferencesMode};
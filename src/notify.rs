@@ -0,0 +1,287 @@
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+
+use crate::condition::{parse_condition, Condition, RunEvent};
+
+/// Program used for desktop notifications unless `--notify-command` names
+/// another `notify-send`-compatible one.
+pub const DEFAULT_NOTIFY_COMMAND: &str = "notify-send";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyBackend {
+    /// `OSC 9` notifications (iTerm2, Windows Terminal, kitty, WezTerm).
+    Osc9,
+    /// `OSC 777` notifications (rxvt-unicode, foot, Ghostty, VTE).
+    Osc777,
+    /// A freedesktop notification through `notify-send`.
+    Desktop,
+    /// Puts the event in the terminal window title.
+    Title,
+}
+
+/// Sends a notification through `backend` whenever `condition` fires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotifyRule {
+    pub backend: NotifyBackend,
+    pub condition: Condition,
+}
+
+/// Parses `<backend>:<condition>`, e.g. `osc9:fail` or `desktop:match:ERROR`.
+pub fn parse_notify_rule(value: &str) -> Result<NotifyRule, String> {
+    let Some((backend, condition)) = value.trim().split_once(':') else {
+        return Err("expected '<backend>:<condition>', e.g. 'desktop:fail'".to_string());
+    };
+    let backend = match backend.to_ascii_lowercase().as_str() {
+        "osc9" => NotifyBackend::Osc9,
+        "osc777" => NotifyBackend::Osc777,
+        "desktop" => NotifyBackend::Desktop,
+        "title" => NotifyBackend::Title,
+        _ => {
+            return Err(format!(
+                "unknown notification backend '{backend}', expected 'osc9', 'osc777', 'desktop' or 'title'"
+            ));
+        }
+    };
+    Ok(NotifyRule {
+        backend,
+        condition: parse_condition(condition)?,
+    })
+}
+
+pub struct Notifier {
+    rules: Vec<NotifyRule>,
+    command: String,
+    children: Vec<Child>,
+    /// The (pane, rule) pairs whose condition held on the pane's last run.
+    holding: Vec<(usize, usize)>,
+    saved_title: bool,
+}
+
+impl Notifier {
+    pub fn new(rules: Vec<NotifyRule>, command: String) -> Self {
+        Self {
+            rules,
+            command,
+            children: Vec::new(),
+            holding: Vec::new(),
+            saved_title: false,
+        }
+    }
+
+    /// Sends the notifications that fire for this run of `pane`'s `command`.
+    /// `fail`, `success` and `match` notify when they start holding rather
+    /// than on every run, while `change` and `always` notify each time.
+    /// Desktop notifications run in the background, and the window title
+    /// comes back once no `title` condition holds. A notification that
+    /// cannot be sent does not stop the others; the first error is returned.
+    pub fn notify(
+        &mut self,
+        out: &mut impl Write,
+        pane: usize,
+        command: &[String],
+        event: &RunEvent<'_>,
+    ) -> io::Result<()> {
+        self.children
+            .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+
+        let title = format!("watch: {}", command.join(" "));
+        let mut failed = None;
+        for (index, rule) in self.rules.iter().enumerate() {
+            let key = (pane, index);
+            let held = self.holding.contains(&key);
+            if !rule.condition.fires(event) {
+                self.holding.retain(|holding| *holding != key);
+                continue;
+            }
+            if held && !matches!(rule.condition, Condition::Change | Condition::Always) {
+                continue;
+            }
+            if !held {
+                self.holding.push(key);
+            }
+            let body = describe(&rule.condition);
+            match rule.backend {
+                NotifyBackend::Osc9 => write!(out, "{}", osc9(&format!("{title}: {body}")))?,
+                NotifyBackend::Osc777 => write!(out, "{}", osc777(&title, body))?,
+                NotifyBackend::Title => {
+                    if !self.saved_title {
+                        // Push the current title so it comes back on exit.
+                        out.write_all(b"\x1b[22;0t")?;
+                        self.saved_title = true;
+                    }
+                    write!(out, "\x1b]2;{}\x07", sanitize(&format!("[{body}] {title}")))?;
+                }
+                NotifyBackend::Desktop => {
+                    let spawned = Command::new(&self.command)
+                        .arg(&title)
+                        .arg(body)
+                        .stdin(Stdio::null())
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .spawn();
+                    match spawned {
+                        Ok(child) => self.children.push(child),
+                        Err(err) => {
+                            failed.get_or_insert_with(|| {
+                                io::Error::new(err.kind(), format!("{}: {err}", self.command))
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        let titled = self
+            .holding
+            .iter()
+            .any(|&(_, index)| self.rules[index].backend == NotifyBackend::Title);
+        if self.saved_title && !titled {
+            out.write_all(b"\x1b[23;0t")?;
+            self.saved_title = false;
+        }
+        out.flush()?;
+        failed.map_or(Ok(()), Err)
+    }
+}
+
+impl Drop for Notifier {
+    fn drop(&mut self) {
+        if self.saved_title {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(b"\x1b[23;0t");
+            let _ = stdout.flush();
+        }
+    }
+}
+
+fn describe(condition: &Condition) -> &'static str {
+    match condition {
+        Condition::Change => "output changed",
+        Condition::Fail => "command failed",
+        Condition::Success => "command succeeded",
+        Condition::Match(_) => "output matched",
+        Condition::Always => "command ran",
+    }
+}
+
+fn osc9(message: &str) -> String {
+    format!("\x1b]9;{}\x07", sanitize(message))
+}
+
+fn osc777(title: &str, body: &str) -> String {
+    // Fields are separated by ';', so it cannot appear inside them.
+    let title = sanitize(title).replace(';', ",");
+    let body = sanitize(body).replace(';', ",");
+    format!("\x1b]777;notify;{title};{body}\x07")
+}
+
+/// Drops control characters that would end or corrupt an OSC sequence.
+fn sanitize(text: &str) -> String {
+    text.chars().filter(|ch| !ch.is_control()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_backend_and_condition() {
+        let rule = parse_notify_rule("desktop:match:ERR;OR").unwrap();
+        assert_eq!(rule.backend, NotifyBackend::Desktop);
+        assert!(matches!(rule.condition, Condition::Match(_)));
+        assert_eq!(parse_notify_rule("OSC9:fail").unwrap().backend, NotifyBackend::Osc9);
+        assert!(parse_notify_rule("fail").is_err());
+        assert!(parse_notify_rule("email:fail").is_err());
+    }
+
+    #[test]
+    fn writes_terminal_notifications() {
+        let rules = vec![
+            parse_notify_rule("osc9:change").unwrap(),
+            parse_notify_rule("osc777:change").unwrap(),
+            parse_notify_rule("osc9:fail").unwrap(),
+        ];
+        let mut notifier = Notifier::new(rules, DEFAULT_NOTIFY_COMMAND.to_string());
+        let mut out = Vec::new();
+        let event = RunEvent {
+            changed: true,
+            success: true,
+            output: "",
+        };
+        notifier
+            .notify(&mut out, 0, &["echo".into(), "a;\x1bb".into()], &event)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\x1b]9;watch: echo a;b: output changed\x07\x1b]777;notify;watch: echo a,b;output changed\x07"
+        );
+    }
+
+    #[test]
+    fn notifies_when_the_condition_starts_holding() {
+        let rules = vec![
+            parse_notify_rule("osc9:fail").unwrap(),
+            parse_notify_rule("title:fail").unwrap(),
+        ];
+        let mut notifier = Notifier::new(rules, DEFAULT_NOTIFY_COMMAND.to_string());
+        let command = ["false".to_string()];
+        let mut run = |pane: usize, success: bool| {
+            let event = RunEvent {
+                changed: false,
+                success,
+                output: "",
+            };
+            let mut out = Vec::new();
+            notifier.notify(&mut out, pane, &command, &event).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let failed = "\x1b]9;watch: false: command failed\x07\x1b[22;0t\x1b]2;[command failed] watch: false\x07";
+        assert_eq!(run(0, false), failed);
+        assert_eq!(run(0, false), "");
+        assert_eq!(run(1, false), "\x1b]9;watch: false: command failed\x07\x1b]2;[command failed] watch: false\x07");
+        assert_eq!(run(0, true), "");
+        assert_eq!(run(1, true), "\x1b[23;0t");
+        assert_eq!(run(0, false), failed);
+    }
+
+    #[test]
+    fn notifies_on_every_change() {
+        let rules = vec![parse_notify_rule("osc9:change").unwrap()];
+        let mut notifier = Notifier::new(rules, DEFAULT_NOTIFY_COMMAND.to_string());
+        let command = ["date".to_string()];
+        let mut run = |changed: bool| {
+            let event = RunEvent {
+                changed,
+                success: true,
+                output: "",
+            };
+            let mut out = Vec::new();
+            notifier.notify(&mut out, 0, &command, &event).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let changed = "\x1b]9;watch: date: output changed\x07";
+        assert_eq!(run(true), changed);
+        assert_eq!(run(true), changed);
+        assert_eq!(run(false), "");
+        assert_eq!(run(true), changed);
+    }
+
+    #[test]
+    fn failed_desktop_notification_does_not_stop_the_others() {
+        let rules = vec![
+            parse_notify_rule("desktop:always").unwrap(),
+            parse_notify_rule("osc9:always").unwrap(),
+        ];
+        let mut notifier = Notifier::new(rules, "/nonexistent/notify-send".to_string());
+        let event = RunEvent {
+            changed: false,
+            success: true,
+            output: "",
+        };
+        let mut out = Vec::new();
+        let err = notifier
+            .notify(&mut out, 0, &["true".to_string()], &event)
+            .unwrap_err();
+        assert!(err.to_string().starts_with("/nonexistent/notify-send: "));
+        assert_eq!(String::from_utf8(out).unwrap(), "\x1b]9;watch: true: command ran\x07");
+    }
+}
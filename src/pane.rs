@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use crate::interval::parse_interval;
+
+/// Separates the commands of a multi-pane watch: `watch -- cmd1 ::: cmd2`.
+pub const PANE_SEPARATOR: &str = ":::";
+
+/// One command shown in its own pane, optionally with its own interval.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaneCommand {
+    pub command: Vec<String>,
+    pub interval: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    /// Panes stacked top to bottom.
+    Horizontal,
+    /// Panes side by side.
+    Vertical,
+}
+
+/// Size of a pane in cells, header included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaneArea {
+    pub columns: u16,
+    pub rows: u16,
+}
+
+pub fn parse_split(raw: &str) -> Result<SplitDirection, String> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "horizontal" | "h" => Ok(SplitDirection::Horizontal),
+        "vertical" | "v" => Ok(SplitDirection::Vertical),
+        _ => Err("expected 'horizontal' or 'vertical'".to_string()),
+    }
+}
+
+/// Splits the command line at `:::`. Every command after the first may start
+/// with `-n <interval>`, `-n<interval>` or `--interval=<interval>` to
+/// override the interval for its pane.
+pub fn split_panes(args: &[String]) -> Result<Vec<PaneCommand>, String> {
+    let mut panes = Vec::new();
+    for (idx, segment) in args.split(|arg| arg == PANE_SEPARATOR).enumerate() {
+        let (interval, command) = if idx == 0 {
+            (None, segment)
+        } else {
+            pane_interval(segment)?
+        };
+        if command.is_empty() {
+            return Err(format!("empty command around '{PANE_SEPARATOR}'"));
+        }
+        panes.push(PaneCommand {
+            command: command.to_vec(),
+            interval,
+        });
+    }
+    Ok(panes)
+}
+
+fn pane_interval(segment: &[String]) -> Result<(Option<Duration>, &[String]), String> {
+    let (raw, rest) = match segment.first().map(String::as_str) {
+        Some("-n" | "--interval") => match segment.get(1) {
+            Some(value) => (value.as_str(), &segment[2..]),
+            None => return Err("option -n requires a value".to_string()),
+        },
+        Some(arg) => {
+            if let Some(value) = arg.strip_prefix("--interval=") {
+                (value, &segment[1..])
+            } else if let Some(interval) = arg
+                .strip_prefix("-n")
+                .and_then(|value| parse_interval(value).ok())
+            {
+                // Anything else starting with `-n` belongs to the command.
+                return Ok((Some(interval), &segment[1..]));
+            } else {
+                return Ok((None, segment));
+            }
+        }
+        None => return Ok((None, segment)),
+    };
+    let interval = parse_interval(raw).map_err(|err| err.to_string())?;
+    Ok((Some(interval), rest))
+}
+
+/// Divides the screen between `count` panes, separated by one-cell dividers.
+/// Earlier panes get the leftover cells.
+pub fn pane_areas(count: usize, split: SplitDirection, columns: u16, rows: u16) -> Vec<PaneArea> {
+    let count = count.max(1);
+    let total = match split {
+        SplitDirection::Horizontal => rows,
+        SplitDirection::Vertical => columns,
+    } as usize;
+    let available = total.saturating_sub(count - 1);
+    (0..count)
+        .map(|idx| {
+            let size = (available / count + usize::from(idx < available % count)) as u16;
+            match split {
+                SplitDirection::Horizontal => PaneArea {
+                    columns,
+                    rows: size,
+                },
+                SplitDirection::Vertical => PaneArea {
+                    columns: size,
+                    rows,
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn splits_commands_with_intervals() {
+        let panes = split_panes(&args(&[
+            "kubectl", "get", "pods", ":::", "-n", "10", "kubectl", "get", "events", ":::",
+            "--interval=1m", "date",
+        ]))
+        .unwrap();
+        assert_eq!(panes.len(), 3);
+        assert_eq!(panes[0].command, args(&["kubectl", "get", "pods"]));
+        assert_eq!(panes[0].interval, None);
+        assert_eq!(panes[1].command, args(&["kubectl", "get", "events"]));
+        assert_eq!(panes[1].interval, Some(Duration::from_secs(10)));
+        assert_eq!(panes[2].interval, Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn keeps_other_arguments_starting_with_n() {
+        let panes = split_panes(&args(&["date", ":::", "-nfoo", "x", ":::", "-n5s", "uptime"]))
+            .unwrap();
+        assert_eq!(panes[1].command, args(&["-nfoo", "x"]));
+        assert_eq!(panes[1].interval, None);
+        assert_eq!(panes[2].command, args(&["uptime"]));
+        assert_eq!(panes[2].interval, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn rejects_empty_panes() {
+        assert!(split_panes(&args(&["date", ":::"])).is_err());
+        assert!(split_panes(&args(&["date", ":::", "-n", "5"])).is_err());
+    }
+
+    #[test]
+    fn areas_share_the_screen_between_dividers() {
+        let areas = pane_areas(3, SplitDirection::Horizontal, 80, 24);
+        let rows: Vec<u16> = areas.iter().map(|area| area.rows).collect();
+        assert_eq!(rows, vec![8, 7, 7]);
+        let areas = pane_areas(2, SplitDirection::Vertical, 81, 24);
+        assert_eq!(areas[0], PaneArea { columns: 40, rows: 24 });
+        assert_eq!(areas[1], PaneArea { columns: 40, rows: 24 });
+    }
+}
@@ -0,0 +1,45 @@
+use regex::Regex;
+
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(source: &str) -> Result<Self, String> {
+        Regex::new(source)
+            .map(Self)
+            .map_err(|err| format!("invalid regular expression '{source}': {err}"))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_pattern() {
+        let err = Pattern::new("(").unwrap_err();
+        assert!(err.contains("invalid regular expression"));
+    }
+
+    #[test]
+    fn compares_by_source() {
+        assert_eq!(Pattern::new("a+").unwrap(), Pattern::new("a+").unwrap());
+        assert_ne!(Pattern::new("a+").unwrap(), Pattern::new("b+").unwrap());
+    }
+}
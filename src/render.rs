@@ -1,9 +1,9 @@
-use std::time::Duration;
-
 use chrono::{DateTime, Local};
-use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use crate::config::ColorMode;
+use crate::grid::{Grid, Line};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TerminalSize {
@@ -22,11 +22,11 @@ pub fn terminal_size() -> TerminalSize {
     let mut cols = env_u16("COLUMNS");
     let mut rows = env_u16("LINES");
 
-    if cols.is_none() || rows.is_none() {
-        if let Ok((term_cols, term_rows)) = crossterm::terminal::size() {
-            cols = cols.or(Some(term_cols));
-            rows = rows.or(Some(term_rows));
-        }
+    if (cols.is_none() || rows.is_none())
+        && let Ok((term_cols, term_rows)) = crossterm::terminal::size()
+    {
+        cols = cols.or(Some(term_cols));
+        rows = rows.or(Some(term_rows));
     }
 
     TerminalSize {
@@ -37,23 +37,33 @@ pub fn terminal_size() -> TerminalSize {
 
 pub fn header_line(
     command: &[String],
-    interval: Duration,
+    schedule: &str,
     now: DateTime<Local>,
     columns: u16,
 ) -> String {
     let right = now.format("%a %b %e %H:%M:%S %Y").to_string();
-    header_line_with_time(command, interval, &right, columns)
+    header_line_with_time(command, schedule, &right, columns)
+}
+
+/// Header for one of several panes: the time is left out when it would
+/// crowd out the command.
+pub fn pane_header(command: &[String], schedule: &str, now: DateTime<Local>, columns: u16) -> String {
+    let right = now.format("%a %b %e %H:%M:%S %Y").to_string();
+    let left_width = UnicodeWidthStr::width(format!("{schedule}: {}", command.join(" ")).as_str());
+    if left_width + 1 + UnicodeWidthStr::width(right.as_str()) > columns as usize {
+        return truncate_to_width(&format!("{schedule}: {}", command.join(" ")), columns as usize);
+    }
+    header_line_with_time(command, schedule, &right, columns)
 }
 
 pub fn header_line_with_time(
     command: &[String],
-    interval: Duration,
+    schedule: &str,
     right: &str,
     columns: u16,
 ) -> String {
-    let secs = interval.as_secs_f64();
     let command_str = command.join(" ");
-    let left = format!("Every {:.1}s: {command_str}", secs);
+    let left = format!("{schedule}: {command_str}");
     let mut left_truncated = truncate_to_width(&left, columns as usize);
 
     let left_width = UnicodeWidthStr::width(left_truncated.as_str());
@@ -75,113 +85,73 @@ pub fn header_line_with_time(
     line
 }
 
-pub fn format_output(
-    output: &[u8],
-    columns: u16,
-    no_wrap: bool,
-    color: ColorMode,
-) -> Vec<String> {
-    let input = String::from_utf8_lossy(output);
-    let text = match color {
-        ColorMode::Always => input.into_owned(),
-        ColorMode::Auto | ColorMode::Never => strip_ansi_text(&input),
-    };
-    format_text(&text, columns, no_wrap)
+pub fn toast_line(message: &str, columns: u16) -> String {
+    reverse_video(&truncate_to_width(&format!(" {message} "), columns as usize))
 }
 
-fn env_u16(key: &str) -> Option<u16> {
-    std::env::var(key)
-        .ok()
-        .and_then(|value| value.trim().parse::<u16>().ok())
-        .filter(|value| *value > 0)
+pub fn reverse_video(text: &str) -> String {
+    format!("\x1b[7m{text}\x1b[0m")
 }
 
-pub fn strip_ansi_text(input: &str) -> String {
-    let stripped = strip_ansi_escapes::strip(input.as_bytes());
-    String::from_utf8_lossy(&stripped).into_owned()
+/// Rule drawn between panes stacked on top of each other.
+pub fn divider_line(columns: u16) -> Line {
+    Line::plain(&"\u{2500}".repeat(columns as usize))
 }
 
-pub fn format_text(text: &str, columns: u16, no_wrap: bool) -> Vec<String> {
-    split_lines(text, columns as usize, no_wrap)
+/// Places pane blocks side by side, padding each row to its pane's width
+/// and separating the panes with a vertical rule.
+pub fn join_columns(blocks: &[Vec<Line>], widths: &[u16]) -> Vec<Line> {
+    let rows = blocks.iter().map(Vec::len).max().unwrap_or(0);
+    (0..rows)
+        .map(|row| {
+            let mut line = Line::default();
+            for (idx, (block, width)) in blocks.iter().zip(widths).enumerate() {
+                if idx > 0 {
+                    line.extend(Line::plain("\u{2502}"));
+                }
+                let mut cell = block.get(row).cloned().unwrap_or_default();
+                cell.pad(*width as usize);
+                line.extend(cell);
+            }
+            line
+        })
+        .collect()
 }
 
-fn split_lines(text: &str, width: usize, no_wrap: bool) -> Vec<String> {
-    let mut lines = Vec::new();
-    let mut current = String::new();
-    let mut col = 0usize;
-    let mut truncated = false;
-    let width = width.max(1);
-
-    let mut chars = text.chars().peekable();
-    while let Some(ch) = chars.next() {
-        if ch == '\r' {
-            continue;
-        }
-
-        if ch == '\n' {
-            lines.push(current);
-            current = String::new();
-            col = 0;
-            truncated = false;
-            continue;
-        }
-
-        if ch == '\x1b' {
-            let seq = consume_escape_sequence(ch, &mut chars);
-            current.push_str(&seq);
-            continue;
-        }
-
-        let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
-        if no_wrap && (truncated || col + ch_width > width) {
-            truncated = true;
-            continue;
-        }
-
-        if !no_wrap && col + ch_width > width {
-            lines.push(current);
-            current = String::new();
-            col = 0;
-        }
-
-        current.push(ch);
-        col += ch_width;
+pub fn format_output(output: &[u8], columns: u16, no_wrap: bool, color: ColorMode) -> Vec<Line> {
+    let mut grid = Grid::parse(&String::from_utf8_lossy(output));
+    if color != ColorMode::Always {
+        grid.clear_styles();
     }
+    grid.wrap(columns as usize, no_wrap)
+}
 
-    lines.push(current);
-    lines
+fn env_u16(key: &str) -> Option<u16> {
+    std::env::var(key)
+        .ok()
+        .and_then(|value| value.trim().parse::<u16>().ok())
+        .filter(|value| *value > 0)
 }
 
-fn consume_escape_sequence(
-    first: char,
-    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
-) -> String {
-    let mut seq = String::new();
-    seq.push(first);
-
-    if let Some('[') = chars.peek().copied() {
-        seq.push(chars.next().unwrap());
-        while let Some(next) = chars.next() {
-            seq.push(next);
-            if ('@'..='~').contains(&next) {
-                break;
-            }
-        }
-    }
+/// The visible text of `input`, without escape sequences.
+pub fn strip_ansi_text(input: &str) -> String {
+    Grid::parse(input).text()
+}
 
-    seq
+pub fn format_text(text: &str, columns: u16, no_wrap: bool) -> Vec<Line> {
+    Grid::parse(text).wrap(columns as usize, no_wrap)
 }
 
 fn truncate_to_width(input: &str, width: usize) -> String {
     let mut out = String::new();
     let mut col = 0usize;
-    for ch in input.chars() {
-        let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
-        if col + ch_width > width {
+    for grapheme in input.graphemes(true) {
+        let grapheme_width = UnicodeWidthStr::width(grapheme);
+        if col + grapheme_width > width {
             break;
         }
-        out.push(ch);
-        col += ch_width;
+        out.push_str(grapheme);
+        col += grapheme_width;
     }
     out
 }
@@ -192,20 +162,64 @@ mod tests {
 
     #[test]
     fn header_right_aligns_time() {
-        let line = header_line_with_time(&["echo".into()], Duration::from_secs(2), "TIME", 20);
+        let line = header_line_with_time(&["echo".into()], "Every 2.0s", "TIME", 20);
         assert_eq!(line.len(), 20);
         assert!(line.ends_with("TIME"));
     }
 
+    #[test]
+    fn toast_line_is_truncated_and_reversed() {
+        assert_eq!(toast_line("saved", 10), "\x1b[7m saved \x1b[0m");
+        assert_eq!(toast_line("saved screenshot", 8), "\x1b[7m saved s\x1b[0m");
+    }
+
+    #[test]
+    fn truncation_keeps_clusters_whole() {
+        let text = "echo \u{1f1fa}\u{1f1f8}e\u{301}";
+        assert_eq!(truncate_to_width(text, 6), "echo ");
+        assert_eq!(truncate_to_width(text, 7), "echo \u{1f1fa}\u{1f1f8}");
+        assert_eq!(truncate_to_width(text, 8), text);
+    }
+
+    #[test]
+    fn pane_header_drops_time_when_narrow() {
+        let now = Local::now();
+        let line = pane_header(&["uptime".into()], "Every 2.0s", now, 30);
+        assert_eq!(line, "Every 2.0s: uptime");
+        let line = pane_header(&["uptime".into()], "Every 2.0s", now, 60);
+        assert_eq!(UnicodeWidthStr::width(line.as_str()), 60);
+    }
+
+    fn plain(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(Line::to_plain).collect()
+    }
+
+    #[test]
+    fn join_columns_pads_panes_to_their_width() {
+        let left = format_text("ab\n\x1b[7mc", 3, false);
+        let right = format_text("xyz", 3, false);
+        let lines = join_columns(&[left, right], &[3, 3]);
+        assert_eq!(lines[0].to_ansi(), "ab \u{2502}xyz");
+        assert_eq!(lines[1].to_ansi(), "\x1b[7mc\x1b[0m  \u{2502}   ");
+    }
+
     #[test]
     fn format_output_wraps_lines() {
         let lines = format_output(b"abcdef", 3, false, ColorMode::Never);
-        assert_eq!(lines, vec!["abc", "def"]);
+        assert_eq!(plain(&lines), vec!["abc", "def"]);
     }
 
     #[test]
     fn format_output_truncates_lines() {
         let lines = format_output(b"abcdef", 3, true, ColorMode::Never);
-        assert_eq!(lines, vec!["abc"]);
+        assert_eq!(plain(&lines), vec!["abc"]);
+    }
+
+    #[test]
+    fn format_output_keeps_layout_with_escape_sequences() {
+        let output = b"\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\ \x1b[1mbold\x1b[0m";
+        let lines = format_output(output, 6, true, ColorMode::Always);
+        assert_eq!(plain(&lines), vec!["link b"]);
+        assert_eq!(lines[0].to_ansi(), "link \x1b[1mb\x1b[0m");
     }
 }
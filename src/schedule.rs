@@ -0,0 +1,316 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local, Offset, TimeZone};
+
+use crate::config::Config;
+use crate::cron::CronSchedule;
+use crate::interval::{format_interval, MAX_INTERVAL_SECS, MIN_INTERVAL_SECS};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Waits `period` after each run, or between run starts when `precise`.
+    Interval { period: Duration, precise: bool },
+    /// Runs on wall-clock multiples of `period` in local time.
+    Aligned(Duration),
+    Cron(CronSchedule),
+    /// Runs only when watched files change.
+    OnChange,
+}
+
+impl Schedule {
+    pub fn from_config(config: &Config) -> Self {
+        if let Some(cron) = &config.cron {
+            Schedule::Cron(cron.clone())
+        } else if !config.watch_paths.is_empty() && !config.watch_fallback && !config.align {
+            Schedule::OnChange
+        } else if config.align {
+            Schedule::Aligned(config.interval)
+        } else {
+            Schedule::Interval {
+                period: config.interval,
+                precise: config.precise,
+            }
+        }
+    }
+
+    /// Replaces the period of interval and aligned schedules, for panes with
+    /// their own `-n`.
+    pub fn with_interval(self, interval: Option<Duration>) -> Self {
+        match (self, interval) {
+            (Schedule::Interval { precise, .. }, Some(period)) => {
+                Schedule::Interval { period, precise }
+            }
+            (Schedule::Aligned(_), Some(period)) => Schedule::Aligned(period),
+            (schedule, _) => schedule,
+        }
+    }
+
+    /// Returns when the next run should start, given when the last one
+    /// started and finished.
+    pub fn next_run(&self, started: Instant, finished: Instant, pacer: &mut Pacer) -> Instant {
+        match self {
+            Schedule::Interval { period, precise } => {
+                let period = pacer.jittered(*period);
+                if *precise {
+                    started + period
+                } else {
+                    finished + period
+                }
+            }
+            Schedule::Aligned(period) => Instant::now() + delay_to_boundary(Local::now(), *period),
+            Schedule::Cron(cron) => Instant::now() + delay_to_cron(Local::now(), cron),
+            Schedule::OnChange => Instant::now() + Duration::from_secs_f64(MAX_INTERVAL_SECS),
+        }
+    }
+
+    /// Multiplies the period by `factor`, clamped to the supported range.
+    /// Returns `false` for schedules without a period.
+    pub fn scale(&mut self, factor: f64) -> bool {
+        let period = match self {
+            Schedule::Interval { period, .. } | Schedule::Aligned(period) => period,
+            Schedule::Cron(_) | Schedule::OnChange => return false,
+        };
+        let secs = (period.as_secs_f64() * factor).clamp(MIN_INTERVAL_SECS, MAX_INTERVAL_SECS);
+        *period = Duration::from_secs_f64(secs);
+        true
+    }
+
+    /// The current wait between runs, including backoff or adaptive
+    /// stretching, for schedules that have one.
+    pub fn period(&self, pacer: &Pacer) -> Option<Duration> {
+        match self {
+            Schedule::Interval { period, .. } => Some(pacer.effective(*period)),
+            Schedule::Aligned(period) => Some(*period),
+            Schedule::Cron(_) | Schedule::OnChange => None,
+        }
+    }
+
+    pub fn label(&self, pacer: &Pacer) -> String {
+        match self {
+            Schedule::Interval { period, .. } => {
+                let effective = pacer.effective(*period);
+                match pacer.reason(*period) {
+                    Some(reason) => format!("Every {} ({reason})", format_interval(effective)),
+                    None => format!("Every {}", format_interval(effective)),
+                }
+            }
+            Schedule::Aligned(period) => format!("Every {} (aligned)", format_interval(*period)),
+            Schedule::Cron(cron) => format!("Cron {}", cron.source()),
+            Schedule::OnChange => "On file change".to_string(),
+        }
+    }
+}
+
+/// Stretches fixed intervals after failures (`--backoff`) or while the
+/// output stays the same (`--adaptive`).
+#[derive(Debug, Clone)]
+pub struct Pacer {
+    backoff_max: Option<Duration>,
+    adaptive_max: Option<Duration>,
+    failures: u32,
+    unchanged: u32,
+    seed: u64,
+}
+
+const BACKOFF_FACTOR: f64 = 2.0;
+const ADAPTIVE_FACTOR: f64 = 1.5;
+const JITTER_RATIO: f64 = 0.1;
+
+impl Pacer {
+    pub fn new(backoff_max: Option<Duration>, adaptive_max: Option<Duration>) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(0);
+        Self {
+            backoff_max,
+            adaptive_max,
+            failures: 0,
+            unchanged: 0,
+            seed: seed | 1,
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.backoff, config.adaptive)
+    }
+
+    pub fn record(&mut self, success: bool, changed: bool) {
+        self.failures = if success {
+            0
+        } else {
+            self.failures.saturating_add(1)
+        };
+        self.unchanged = if changed {
+            0
+        } else {
+            self.unchanged.saturating_add(1)
+        };
+    }
+
+    pub fn effective(&self, period: Duration) -> Duration {
+        let backoff = self
+            .backoff_max
+            .map(|max| stretch(period, BACKOFF_FACTOR, self.failures, max));
+        let adaptive = self
+            .adaptive_max
+            .map(|max| stretch(period, ADAPTIVE_FACTOR, self.unchanged, max));
+        period
+            .max(backoff.unwrap_or(period))
+            .max(adaptive.unwrap_or(period))
+    }
+
+    fn reason(&self, period: Duration) -> Option<&'static str> {
+        let effective = self.effective(period);
+        if effective == period {
+            None
+        } else if self.backoff_max.is_some() && self.failures > 0 {
+            Some("backoff")
+        } else {
+            Some("adaptive")
+        }
+    }
+
+    /// Like `effective`, with up to 10% random jitter while backing off so
+    /// that several watchers of the same endpoint spread out.
+    fn jittered(&mut self, period: Duration) -> Duration {
+        let effective = self.effective(period);
+        if self.backoff_max.is_none() || self.failures == 0 || effective == period {
+            return effective;
+        }
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        let unit = (self.seed >> 11) as f64 / (1u64 << 53) as f64;
+        effective.mul_f64(1.0 + JITTER_RATIO * (unit * 2.0 - 1.0))
+    }
+}
+
+fn stretch(period: Duration, factor: f64, steps: u32, max: Duration) -> Duration {
+    let scaled = period.as_secs_f64() * factor.powi(steps.min(64) as i32);
+    Duration::from_secs_f64(scaled.min(max.as_secs_f64()).max(period.as_secs_f64()))
+}
+
+fn delay_to_boundary<Tz: TimeZone>(now: DateTime<Tz>, period: Duration) -> Duration {
+    let period_ms = period.as_millis().max(1) as i64;
+    let offset_ms = i64::from(now.offset().fix().local_minus_utc()) * 1_000;
+    let local_ms = now.timestamp_millis() + offset_ms;
+    let next = (local_ms.div_euclid(period_ms) + 1) * period_ms;
+    Duration::from_millis((next - local_ms) as u64)
+}
+
+fn delay_to_cron(now: DateTime<Local>, cron: &CronSchedule) -> Duration {
+    let mut after = now.naive_local();
+    while let Some(next) = cron.next_after(after) {
+        // Skip local times that do not exist because of a DST jump.
+        if let Some(target) = Local.from_local_datetime(&next).earliest() {
+            return (target - now).to_std().unwrap_or(Duration::ZERO);
+        }
+        after = next;
+    }
+    Duration::from_secs_f64(MAX_INTERVAL_SECS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, NaiveDate};
+
+    fn fixed(h: u32, m: u32, s: u32, ms: u32) -> DateTime<FixedOffset> {
+        let naive = NaiveDate::from_ymd_opt(2024, 3, 1)
+            .unwrap()
+            .and_hms_milli_opt(h, m, s, ms)
+            .unwrap();
+        FixedOffset::east_opt(3_600)
+            .unwrap()
+            .from_local_datetime(&naive)
+            .unwrap()
+    }
+
+    #[test]
+    fn aligned_waits_for_next_boundary() {
+        let delay = delay_to_boundary(fixed(10, 0, 42, 500), Duration::from_secs(60));
+        assert_eq!(delay, Duration::from_millis(17_500));
+    }
+
+    #[test]
+    fn aligned_uses_local_time_for_long_periods() {
+        let delay = delay_to_boundary(fixed(23, 30, 0, 0), Duration::from_secs(3_600));
+        assert_eq!(delay, Duration::from_secs(1_800));
+    }
+
+    #[test]
+    fn label_humanizes_interval() {
+        let schedule = Schedule::Interval {
+            period: Duration::from_secs(5_400),
+            precise: false,
+        };
+        assert_eq!(schedule.label(&Pacer::new(None, None)), "Every 1h30m");
+    }
+
+    #[test]
+    fn backoff_grows_until_cap_and_resets() {
+        let period = Duration::from_secs(1);
+        let mut pacer = Pacer::new(Some(Duration::from_secs(5)), None);
+        pacer.record(false, false);
+        assert_eq!(pacer.effective(period), Duration::from_secs(2));
+        pacer.record(false, false);
+        pacer.record(false, false);
+        assert_eq!(pacer.effective(period), Duration::from_secs(5));
+        let jittered = pacer.jittered(period);
+        assert!(
+            jittered >= Duration::from_millis(4_500) && jittered <= Duration::from_millis(5_500)
+        );
+        pacer.record(true, false);
+        assert_eq!(pacer.effective(period), period);
+    }
+
+    #[test]
+    fn adaptive_stretches_while_unchanged() {
+        let period = Duration::from_secs(2);
+        let schedule = Schedule::Interval {
+            period,
+            precise: false,
+        };
+        let mut pacer = Pacer::new(None, Some(Duration::from_secs(60)));
+        pacer.record(true, false);
+        pacer.record(true, false);
+        assert_eq!(pacer.effective(period), Duration::from_millis(4_500));
+        assert_eq!(schedule.label(&pacer), "Every 4.5s (adaptive)");
+        pacer.record(true, true);
+        assert_eq!(pacer.effective(period), period);
+    }
+
+    #[test]
+    fn scale_clamps_to_interval_limits() {
+        let mut schedule = Schedule::Interval {
+            period: Duration::from_millis(150),
+            precise: false,
+        };
+        assert!(schedule.scale(0.5));
+        assert_eq!(
+            schedule,
+            Schedule::Interval {
+                period: Duration::from_secs_f64(MIN_INTERVAL_SECS),
+                precise: false,
+            }
+        );
+        let mut cron = Schedule::Cron(crate::cron::parse_cron("@hourly").unwrap());
+        assert!(!cron.scale(2.0));
+    }
+
+    #[test]
+    fn precise_interval_counts_from_start() {
+        let schedule = Schedule::Interval {
+            period: Duration::from_secs(2),
+            precise: true,
+        };
+        let started = Instant::now();
+        let finished = started + Duration::from_millis(500);
+        let mut pacer = Pacer::new(None, None);
+        assert_eq!(
+            schedule.next_run(started, finished, &mut pacer),
+            started + Duration::from_secs(2)
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::io::{self, Write};
+
+use crossterm::cursor::MoveTo;
+use crossterm::queue;
+use crossterm::terminal::{
+    BeginSynchronizedUpdate, Clear, ClearType, EndSynchronizedUpdate, ScrollUp,
+};
+
+use crate::grid::Line;
+use crate::render::TerminalSize;
+
+/// The rows last painted on the terminal. Drawing a frame only rewrites the
+/// cells that differ, inside a synchronized update so that terminals which
+/// support it show the new frame at once.
+#[derive(Debug, Default)]
+pub struct Screen {
+    rows: Vec<Line>,
+    size: Option<TerminalSize>,
+    /// Rows from the first one down moved up by the second, see `scroll`.
+    pending_scroll: Option<(usize, usize)>,
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notes that the rows from `top` down moved up by `count`, as when a
+    /// log grows, so that the next draw scrolls them on the terminal rather
+    /// than repainting each one.
+    pub fn scroll(&mut self, top: usize, count: usize) {
+        self.pending_scroll = (count > 0).then_some((top, count));
+    }
+
+    /// Brings the screen up to date with `frame`. A size change repaints
+    /// from scratch; rows past the bottom are left out.
+    pub fn draw(&mut self, out: &mut impl Write, frame: &[Line], size: TerminalSize) -> io::Result<()> {
+        queue!(out, BeginSynchronizedUpdate)?;
+        let scroll = self.pending_scroll.take();
+        let rows = size.rows as usize;
+        if self.size != Some(size) {
+            queue!(out, Clear(ClearType::All))?;
+            self.rows.clear();
+            self.size = Some(size);
+        } else if let Some((top, count)) = scroll
+            && top + count < rows
+        {
+            // Scroll only the region below `top`, then reset it.
+            write!(out, "\x1b[{};{rows}r", top + 1)?;
+            queue!(out, ScrollUp(count as u16))?;
+            out.write_all(b"\x1b[r")?;
+            self.rows.resize(rows, Line::default());
+            self.rows.drain(top..top + count);
+            self.rows.resize(rows, Line::default());
+        }
+        let height = frame.len().max(self.rows.len()).min(rows);
+        let blank = Line::default();
+        for row in 0..height {
+            let old = self.rows.get(row).unwrap_or(&blank);
+            let new = frame.get(row).unwrap_or(&blank);
+            paint_row(out, row, old, new, size.columns)?;
+        }
+        self.rows = frame.iter().take(rows).cloned().collect();
+        queue!(out, EndSynchronizedUpdate)?;
+        out.flush()
+    }
+
+    /// Paints a single row over the current frame, e.g. a toast, and
+    /// remembers it so the next draw puts the frame back.
+    pub fn draw_row(
+        &mut self,
+        out: &mut impl Write,
+        row: usize,
+        line: Line,
+        size: TerminalSize,
+    ) -> io::Result<()> {
+        if row >= size.rows as usize {
+            return Ok(());
+        }
+        if self.size != Some(size) {
+            // Nothing drawn yet at this size, e.g. right after a resize.
+            queue!(out, MoveTo(0, row as u16), Clear(ClearType::CurrentLine))?;
+            out.write_all(line.to_ansi().as_bytes())?;
+            return out.flush();
+        }
+        if self.rows.len() <= row {
+            self.rows.resize(row + 1, Line::default());
+        }
+        paint_row(out, row, &self.rows[row], &line, size.columns)?;
+        self.rows[row] = line;
+        out.flush()
+    }
+}
+
+/// Rewrites the part of a row from the first cell that differs.
+fn paint_row(out: &mut impl Write, row: usize, old: &Line, new: &Line, columns: u16) -> io::Result<()> {
+    if old == new {
+        return Ok(());
+    }
+    let same = old
+        .cells
+        .iter()
+        .zip(&new.cells)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let col: usize = new.cells[..same].iter().map(|cell| cell.width).sum();
+    let rest = Line {
+        cells: new.cells[same..].to_vec(),
+    };
+    queue!(out, MoveTo(col as u16, row as u16))?;
+    out.write_all(rest.to_ansi().as_bytes())?;
+    // Erasing right after a full-width row would take its last cell with
+    // it, as the cursor still sits on that cell.
+    let width = new.width();
+    if old.width() > width && width < columns as usize {
+        queue!(out, Clear(ClearType::UntilNewLine))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: TerminalSize = TerminalSize {
+        columns: 10,
+        rows: 3,
+    };
+
+    fn draw(screen: &mut Screen, frame: &[&str], size: TerminalSize) -> String {
+        let frame: Vec<Line> = frame.iter().map(|line| Line::plain(line)).collect();
+        let mut out = Vec::new();
+        screen.draw(&mut out, &frame, size).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .replace("\x1b[?2026h", "")
+            .replace("\x1b[?2026l", "")
+    }
+
+    #[test]
+    fn repaints_only_changed_cells() {
+        let mut screen = Screen::new();
+        assert_eq!(
+            draw(&mut screen, &["header", "", "12:00:01"], SIZE),
+            "\x1b[2J\x1b[1;1Hheader\x1b[3;1H12:00:01"
+        );
+        assert_eq!(draw(&mut screen, &["header", "", "12:00:02"], SIZE), "\x1b[3;8H2");
+        assert_eq!(draw(&mut screen, &["header", "", "12:00:02"], SIZE), "");
+        assert_eq!(draw(&mut screen, &["head"], SIZE), "\x1b[1;5H\x1b[K\x1b[3;1H\x1b[K");
+    }
+
+    #[test]
+    fn size_change_repaints_everything() {
+        let mut screen = Screen::new();
+        draw(&mut screen, &["a", "b", "c", "d"], SIZE);
+        let wider = TerminalSize {
+            columns: 20,
+            rows: 2,
+        };
+        assert_eq!(
+            draw(&mut screen, &["a", "b", "c"], wider),
+            "\x1b[2J\x1b[1;1Ha\x1b[2;1Hb"
+        );
+    }
+
+    #[test]
+    fn scrolls_region_below_header() {
+        let mut screen = Screen::new();
+        let size = TerminalSize {
+            columns: 10,
+            rows: 4,
+        };
+        draw(&mut screen, &["header", "a", "b", "c"], size);
+        screen.scroll(1, 2);
+        assert_eq!(
+            draw(&mut screen, &["header", "c", "d", "e"], size),
+            "\x1b[2;4r\x1b[2S\x1b[r\x1b[3;1Hd\x1b[4;1He"
+        );
+    }
+
+    #[test]
+    fn overlay_row_is_restored_by_next_draw() {
+        let mut screen = Screen::new();
+        draw(&mut screen, &["abc", "def"], SIZE);
+        let mut out = Vec::new();
+        screen.draw_row(&mut out, 2, Line::plain("toast"), SIZE).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\x1b[3;1Htoast");
+        assert_eq!(draw(&mut screen, &["abc", "def"], SIZE), "\x1b[3;1H\x1b[K");
+    }
+}
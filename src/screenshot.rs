@@ -3,13 +3,103 @@ use std::path::{Path, PathBuf};
 
 use chrono::Local;
 
-pub fn save_screenshot(dir: &Path, frame: &[String]) -> io::Result<PathBuf> {
+use crate::grid::Line;
+
+const SCREENSHOT_PREFIX: &str = "watch-";
+const SCREENSHOT_SUFFIX: &str = ".txt";
+
+/// Saves the text of `frame`, without colors or highlights.
+pub fn save_screenshot(dir: &Path, frame: &[Line]) -> io::Result<PathBuf> {
     std::fs::create_dir_all(dir)?;
-    let name = format!("watch-{}.txt", Local::now().format("%Y%m%d-%H%M%S"));
+    let name = format!(
+        "{SCREENSHOT_PREFIX}{}{SCREENSHOT_SUFFIX}",
+        Local::now().format("%Y%m%d-%H%M%S%.3f")
+    );
     let path = dir.join(name);
     let mut file = std::fs::File::create(&path)?;
     for line in frame {
-        writeln!(file, "{line}")?;
+        writeln!(file, "{}", line.to_plain().trim_end())?;
     }
     Ok(path)
 }
+
+/// Removes the oldest screenshots in `dir` so that at most `keep` remain.
+pub fn prune_screenshots(dir: &Path, keep: usize) -> io::Result<()> {
+    let mut shots = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if is_screenshot_name(&name) && entry.file_type()?.is_file() {
+            shots.push(name);
+        }
+    }
+
+    if shots.len() <= keep {
+        return Ok(());
+    }
+
+    shots.sort();
+    let excess = shots.len() - keep;
+    for name in shots.into_iter().take(excess) {
+        std::fs::remove_file(dir.join(name))?;
+    }
+    Ok(())
+}
+
+/// Whether `name` is exactly what `save_screenshot` writes, e.g.
+/// `watch-20240101-120000.000.txt`, so that other files are never pruned.
+fn is_screenshot_name(name: &str) -> bool {
+    let Some(stamp) = name
+        .strip_prefix(SCREENSHOT_PREFIX)
+        .and_then(|rest| rest.strip_suffix(SCREENSHOT_SUFFIX))
+    else {
+        return false;
+    };
+    let pattern = b"########-######.###";
+    stamp.len() == pattern.len()
+        && stamp.bytes().zip(pattern).all(|(byte, &expected)| match expected {
+            b'#' => byte.is_ascii_digit(),
+            _ => byte == expected,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_keeps_newest_screenshots() {
+        let dir = std::env::temp_dir().join(format!("watch-prune-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "watch-20240101-000000.000.txt",
+            "watch-20240101-000001.000.txt",
+            "watch-20240101-000002.000.txt",
+            "notes.txt",
+            "watch-list.txt",
+            "watch-20240101-000000.txt",
+        ] {
+            std::fs::write(dir.join(name), "x").unwrap();
+        }
+
+        prune_screenshots(&dir, 2).unwrap();
+
+        let mut remaining: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        remaining.sort();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            remaining,
+            vec![
+                "notes.txt",
+                "watch-20240101-000000.txt",
+                "watch-20240101-000001.000.txt",
+                "watch-20240101-000002.000.txt",
+                "watch-list.txt",
+            ]
+        );
+    }
+}
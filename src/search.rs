@@ -0,0 +1,126 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::ansi::{Color, Style};
+use crate::grid::{Grid, Line};
+
+/// A search through the output, kept across refreshes so the highlights
+/// follow the text. Case is ignored unless the query has capitals.
+#[derive(Debug, Clone)]
+pub struct Search {
+    query: String,
+    regex: Regex,
+    /// The match `n` and `N` last moved to.
+    pub current: usize,
+}
+
+impl Search {
+    /// `None` for an empty query.
+    pub fn new(query: &str) -> Option<Self> {
+        if query.is_empty() {
+            return None;
+        }
+        let regex = RegexBuilder::new(&regex::escape(query))
+            .case_insensitive(!query.chars().any(char::is_uppercase))
+            .build()
+            .ok()?;
+        Some(Self {
+            query: query.to_string(),
+            regex,
+            current: 0,
+        })
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Highlights every match in `grid` and wraps it like `Grid::wrap`.
+    /// Also returns the row each match starts on.
+    pub fn apply(&self, grid: &Grid, width: usize, no_wrap: bool) -> (Vec<Line>, Vec<usize>) {
+        let mut grid = grid.clone();
+        // (line, first cell, end cell) of each match, in order.
+        let mut matches = Vec::new();
+        for (idx, line) in grid.lines.iter().enumerate() {
+            let mut offsets = Vec::with_capacity(line.cells.len() + 1);
+            let mut text = String::new();
+            for cell in &line.cells {
+                offsets.push(text.len());
+                text.push_str(&cell.text);
+            }
+            offsets.push(text.len());
+            for found in self.regex.find_iter(&text) {
+                // Cells overlapping the match, so clusters are never split.
+                let first = offsets.partition_point(|&offset| offset <= found.start()) - 1;
+                let end = offsets.partition_point(|&offset| offset < found.end());
+                matches.push((idx, first, end));
+            }
+        }
+
+        let current = self.current.checked_rem(matches.len());
+        for (nth, &(idx, first, end)) in matches.iter().enumerate() {
+            let style = Style {
+                fg: Some(Color::Indexed(0)),
+                bg: Some(Color::Indexed(3)),
+                reverse: Some(nth) == current,
+                ..Style::default()
+            };
+            for cell in &mut grid.lines[idx].cells[first..end] {
+                cell.style = style;
+            }
+        }
+
+        let rows = grid.wrap_indexed(width, no_wrap);
+        let match_rows = matches
+            .iter()
+            .map(|&(idx, first, _)| {
+                rows.partition_point(|&(line, start, _)| (line, start) <= (idx, first))
+                    .saturating_sub(1)
+            })
+            .collect();
+        let rows = rows.into_iter().map(|(_, _, row)| row).collect();
+        (rows, match_rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlighted(row: &Line) -> String {
+        row.cells
+            .iter()
+            .map(|cell| match (cell.style.bg.is_some(), cell.style.reverse) {
+                (true, true) => cell.text.to_uppercase(),
+                (true, false) => format!("[{}]", cell.text),
+                _ => cell.text.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn highlights_matches_and_finds_their_rows() {
+        let grid = Grid::parse("one Error\nok\nerror two errors");
+        let mut search = Search::new("error").unwrap();
+        search.current = 1;
+        let (rows, matches) = search.apply(&grid, 10, false);
+        assert_eq!(matches, vec![0, 2, 3]);
+        assert_eq!(highlighted(&rows[0]), "one [E][r][r][o][r]");
+        assert_eq!(highlighted(&rows[2]), "ERROR two ");
+        assert_eq!(highlighted(&rows[3]), "[e][r][r][o][r]s");
+    }
+
+    #[test]
+    fn capitals_make_the_search_case_sensitive() {
+        let grid = Grid::parse("error Error");
+        let (_, matches) = Search::new("Error").unwrap().apply(&grid, 80, false);
+        assert_eq!(matches.len(), 1);
+        assert!(Search::new("").is_none());
+    }
+
+    #[test]
+    fn matches_cover_whole_clusters() {
+        let grid = Grid::parse("cafe\u{301}!");
+        let (rows, _) = Search::new("cafe").unwrap().apply(&grid, 80, false);
+        assert_eq!(highlighted(&rows[0]), "CAFE\u{301}!");
+    }
+}
@@ -0,0 +1,928 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use toml::{Table, Value};
+
+use crate::cli::{parse_env_pair, Cli};
+use crate::condition::{parse_condition, Condition};
+use crate::config::{ColorMode, DifferencesMode};
+use crate::cron::{parse_cron, CronSchedule};
+use crate::filter::{parse_filter_checks, FilterChecks};
+use crate::input::parse_action;
+use crate::interval::{parse_duration, parse_interval};
+use crate::keymap::{parse_sequence, KeyBinding};
+use crate::notify::{parse_notify_rule, NotifyRule};
+use crate::pane::{parse_split, SplitDirection};
+use crate::pattern::Pattern;
+
+/// One layer of option values. Layers are merged with precedence
+/// config file < profile < environment < command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Settings {
+    pub interval: Option<Duration>,
+    pub precise: Option<bool>,
+    pub align: Option<bool>,
+    pub cron: Option<CronSchedule>,
+    pub backoff: Option<Duration>,
+    pub adaptive: Option<Duration>,
+    pub no_title: Option<bool>,
+    pub no_wrap: Option<bool>,
+    pub tab_width: Option<usize>,
+    pub differences: Option<DifferencesMode>,
+    pub beep: Option<bool>,
+    pub beep_on: Option<Vec<Condition>>,
+    pub bell_gap: Option<Duration>,
+    pub errexit: Option<bool>,
+    pub chgexit: Option<bool>,
+    pub equexit: Option<u32>,
+    pub follow: Option<bool>,
+    pub separator: Option<bool>,
+    pub dedup: Option<bool>,
+    pub no_rerun: Option<bool>,
+    pub exec: Option<bool>,
+    pub color: Option<ColorMode>,
+    pub shotsdir: Option<PathBuf>,
+    pub shot_on: Option<Vec<Condition>>,
+    pub shots_keep: Option<usize>,
+    pub watch_paths: Option<Vec<PathBuf>>,
+    pub watch_excludes: Option<Vec<String>>,
+    pub watch_debounce: Option<Duration>,
+    pub watch_fallback: Option<bool>,
+    pub shell: Option<String>,
+    pub shell_args: Option<Vec<String>>,
+    pub chdir: Option<PathBuf>,
+    pub env: Option<Vec<(String, String)>>,
+    pub env_clear: Option<bool>,
+    pub stdin: Option<PathBuf>,
+    pub split: Option<SplitDirection>,
+    pub filter: Option<Pattern>,
+    pub filter_context: Option<usize>,
+    pub filter_invert: Option<bool>,
+    pub filter_checks: Option<FilterChecks>,
+    pub on_change: Option<String>,
+    pub on_fail: Option<String>,
+    pub on_recover: Option<String>,
+    pub hook_gap: Option<Duration>,
+    pub notify: Option<Vec<NotifyRule>>,
+    pub notify_command: Option<String>,
+    /// Actions bound to other keys than the defaults; only read from the
+    /// config file.
+    pub keys: Option<Vec<KeyBinding>>,
+    pub command: Option<Vec<String>>,
+}
+
+/// Expands to `$macro!(args, <every Settings field>)`.
+macro_rules! with_fields {
+    ($macro:ident!($($arg:ident),*)) => {
+        $macro!(
+            $($arg,)*
+            interval,
+            precise,
+            align,
+            cron,
+            backoff,
+            adaptive,
+            no_title,
+            no_wrap,
+            tab_width,
+            differences,
+            beep,
+            beep_on,
+            bell_gap,
+            errexit,
+            chgexit,
+            equexit,
+            follow,
+            separator,
+            dedup,
+            no_rerun,
+            exec,
+            color,
+            shotsdir,
+            shot_on,
+            shots_keep,
+            watch_paths,
+            watch_excludes,
+            watch_debounce,
+            watch_fallback,
+            shell,
+            shell_args,
+            chdir,
+            env,
+            env_clear,
+            stdin,
+            split,
+            filter,
+            filter_context,
+            filter_invert,
+            filter_checks,
+            on_change,
+            on_fail,
+            on_recover,
+            hook_gap,
+            notify,
+            notify_command,
+            keys,
+            command,
+        )
+    };
+}
+
+macro_rules! merge_fields {
+    ($base:ident, $over:ident, $($field:ident),* $(,)?) => {
+        Settings {
+            $($field: $over.$field.or($base.$field)),*
+        }
+    };
+}
+
+/// Whether `$settings` sets the field `$var` is named after, e.g.
+/// `WATCH_NO_TITLE` for `no_title`.
+macro_rules! sets_field {
+    ($settings:ident, $var:ident, $($field:ident),* $(,)?) => {
+        false $(|| ($var.eq_ignore_ascii_case(concat!("WATCH_", stringify!($field)))
+            && $settings.$field.is_some()))*
+    };
+}
+
+impl Settings {
+    /// Returns `self` with every field that is set in `over` replaced.
+    /// Options that cannot be combined with ones set in `over`, such as a
+    /// `cron` under `interval`, are dropped so that the higher layer wins.
+    pub fn merge(self, over: Settings) -> Settings {
+        let mut base = self;
+        let on = |value: Option<bool>| value == Some(true);
+        if over.cron.is_some() {
+            base.interval = None;
+            base.precise = None;
+            base.align = None;
+            base.backoff = None;
+            base.adaptive = None;
+        }
+        if over.interval.is_some() || on(over.precise) || on(over.align) {
+            base.cron = None;
+        }
+        if on(over.align) {
+            base.precise = None;
+            base.backoff = None;
+            base.adaptive = None;
+        }
+        if on(over.precise) {
+            base.align = None;
+        }
+        if over.backoff.is_some() || over.adaptive.is_some() {
+            base.cron = None;
+            base.align = None;
+        }
+        if on(over.follow) {
+            base.differences = None;
+            base.chgexit = None;
+            base.equexit = None;
+        }
+        if over.differences.is_some() || on(over.chgexit) || over.equexit.is_some() {
+            base.follow = None;
+        }
+        with_fields!(merge_fields!(base, over))
+    }
+
+    pub fn from_cli(cli: Cli) -> Result<Settings, String> {
+        let interval = cli
+            .interval
+            .as_deref()
+            .map(parse_interval)
+            .transpose()
+            .map_err(|err| err.to_string())?;
+        let backoff = cli
+            .backoff
+            .as_deref()
+            .map(parse_interval)
+            .transpose()
+            .map_err(|err| format!("--backoff: {err}"))?;
+        let adaptive = cli
+            .adaptive
+            .as_deref()
+            .map(parse_interval)
+            .transpose()
+            .map_err(|err| format!("--adaptive: {err}"))?;
+        let watch_debounce = cli
+            .watch_debounce
+            .as_deref()
+            .map(parse_duration)
+            .transpose()
+            .map_err(|err| format!("--watch-debounce: {err}"))?;
+        let bell_gap = cli
+            .bell_gap
+            .as_deref()
+            .map(parse_duration)
+            .transpose()
+            .map_err(|err| format!("--bell-gap: {err}"))?;
+        let hook_gap = cli
+            .hook_gap
+            .as_deref()
+            .map(parse_duration)
+            .transpose()
+            .map_err(|err| format!("--hook-gap: {err}"))?;
+
+        let color = match (cli.color, cli.no_color) {
+            (true, true) => {
+                return Err("options --color and --no-color are mutually exclusive".to_string());
+            }
+            (true, false) => Some(ColorMode::Always),
+            (false, true) => Some(ColorMode::Never),
+            (false, false) => None,
+        };
+
+        let differences = if cli.differences.is_some() {
+            cli.differences
+        } else if cli.differences_flag {
+            Some(DifferencesMode::Changes)
+        } else {
+            None
+        };
+
+        Ok(Settings {
+            interval,
+            precise: switch(cli.precise, cli.no_precise),
+            align: switch(cli.align, cli.no_align),
+            cron: cli.cron,
+            backoff,
+            adaptive,
+            no_title: switch(cli.no_title, cli.title),
+            no_wrap: switch(cli.no_wrap, cli.wrap),
+            tab_width: cli.tab_width,
+            differences,
+            beep: switch(cli.beep, cli.no_beep),
+            beep_on: non_empty(cli.beep_on),
+            bell_gap,
+            errexit: switch(cli.errexit, cli.no_errexit),
+            chgexit: switch(cli.chgexit, cli.no_chgexit),
+            equexit: cli.equexit,
+            follow: switch(cli.follow, cli.no_follow),
+            separator: switch(cli.separator, cli.no_separator),
+            dedup: switch(cli.dedup, cli.no_dedup),
+            no_rerun: switch(cli.no_rerun, cli.rerun),
+            exec: switch(cli.exec, cli.no_exec),
+            color,
+            shotsdir: cli.shotsdir,
+            shot_on: non_empty(cli.shot_on),
+            shots_keep: cli.shots_keep,
+            watch_paths: non_empty(cli.watch_path),
+            watch_excludes: non_empty(cli.watch_exclude),
+            watch_debounce,
+            watch_fallback: switch(cli.watch_fallback, cli.no_watch_fallback),
+            shell: cli.shell,
+            shell_args: non_empty(cli.shell_arg),
+            chdir: cli.chdir,
+            env: non_empty(cli.env),
+            env_clear: switch(cli.env_clear, cli.no_env_clear),
+            stdin: cli.stdin,
+            split: cli.split,
+            filter: cli.filter,
+            filter_context: cli.filter_context,
+            filter_invert: switch(cli.filter_invert, cli.no_filter_invert),
+            filter_checks: cli.filter_checks,
+            on_change: cli.on_change,
+            on_fail: cli.on_fail,
+            on_recover: cli.on_recover,
+            hook_gap,
+            notify: non_empty(cli.notify),
+            notify_command: cli.notify_command,
+            keys: None,
+            command: non_empty(cli.command),
+        })
+    }
+
+    /// Like `from_env_with`, but a variable that does not parse is skipped
+    /// when `over` sets its option anyway, so that `-n 5` still works with a
+    /// broken `WATCH_INTERVAL`.
+    pub fn from_env_under(
+        lookup: impl Fn(&str) -> Option<String>,
+        over: &Settings,
+    ) -> Result<Settings, EnvError> {
+        let mut skipped: Vec<String> = Vec::new();
+        loop {
+            let result = Self::from_env_with(|key| {
+                if skipped.iter().any(|skip| skip == key) {
+                    None
+                } else {
+                    lookup(key)
+                }
+            });
+            match result {
+                Ok(settings) => return Ok(settings),
+                Err(err) if over.sets_var(&err.var) => skipped.push(err.var),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Whether the option the environment variable `var` sets is set here.
+    fn sets_var(&self, var: &str) -> bool {
+        let settings = self;
+        let var = match var {
+            "WATCH_WATCH_PATH" => "WATCH_WATCH_PATHS",
+            "WATCH_WATCH_EXCLUDE" => "WATCH_WATCH_EXCLUDES",
+            "WATCH_SHELL_ARG" => "WATCH_SHELL_ARGS",
+            var => var,
+        };
+        with_fields!(sets_field!(settings, var))
+    }
+
+    /// Reads `WATCH_<OPTION>` variables, named after the long options with
+    /// dashes turned into underscores. Empty variables are ignored.
+    pub fn from_env_with(lookup: impl Fn(&str) -> Option<String>) -> Result<Settings, EnvError> {
+        let var = |key: &str| lookup(key).filter(|value| !value.trim().is_empty());
+        let var: &dyn Fn(&str) -> Option<String> = &var;
+        let interval = |raw: &str| parse_interval(raw).map_err(|err| err.to_string());
+
+        Ok(Settings {
+            interval: env(var, "WATCH_INTERVAL", interval)?,
+            precise: env(var, "WATCH_PRECISE", parse_bool)?,
+            align: env(var, "WATCH_ALIGN", parse_bool)?,
+            cron: env(var, "WATCH_CRON", |raw| {
+                parse_cron(raw).map_err(|err| err.to_string())
+            })?,
+            backoff: env(var, "WATCH_BACKOFF", interval)?,
+            adaptive: env(var, "WATCH_ADAPTIVE", interval)?,
+            no_title: env(var, "WATCH_NO_TITLE", parse_bool)?,
+            no_wrap: env(var, "WATCH_NO_WRAP", parse_bool)?,
+            tab_width: env(var, "WATCH_TAB_WIDTH", parse_count)?,
+            differences: env(var, "WATCH_DIFFERENCES", differences)?,
+            beep: env(var, "WATCH_BEEP", parse_bool)?,
+            beep_on: env(var, "WATCH_BEEP_ON", |raw| {
+                split_list(raw).iter().map(|item| parse_condition(item)).collect()
+            })?,
+            bell_gap: env(var, "WATCH_BELL_GAP", |raw| {
+                parse_duration(raw).map_err(|err| err.to_string())
+            })?,
+            errexit: env(var, "WATCH_ERREXIT", parse_bool)?,
+            chgexit: env(var, "WATCH_CHGEXIT", parse_bool)?,
+            equexit: env(var, "WATCH_EQUEXIT", parse_count)?,
+            follow: env(var, "WATCH_FOLLOW", parse_bool)?,
+            separator: env(var, "WATCH_SEPARATOR", parse_bool)?,
+            dedup: env(var, "WATCH_DEDUP", parse_bool)?,
+            no_rerun: env(var, "WATCH_NO_RERUN", parse_bool)?,
+            exec: env(var, "WATCH_EXEC", parse_bool)?,
+            color: env(var, "WATCH_COLOR", parse_color)?,
+            shotsdir: env(var, "WATCH_SHOTSDIR", |raw| Ok(PathBuf::from(raw)))?,
+            shot_on: env(var, "WATCH_SHOT_ON", |raw| {
+                split_list(raw).iter().map(|item| parse_condition(item)).collect()
+            })?,
+            shots_keep: env(var, "WATCH_SHOTS_KEEP", parse_count)?,
+            watch_paths: env(var, "WATCH_WATCH_PATH", |raw| {
+                Ok(std::env::split_paths(raw).collect())
+            })?,
+            watch_excludes: env(var, "WATCH_WATCH_EXCLUDE", |raw| Ok(split_list(raw)))?,
+            watch_debounce: env(var, "WATCH_WATCH_DEBOUNCE", |raw| {
+                parse_duration(raw).map_err(|err| err.to_string())
+            })?,
+            watch_fallback: env(var, "WATCH_WATCH_FALLBACK", parse_bool)?,
+            shell: env(var, "WATCH_SHELL", |raw| Ok(raw.to_string()))?,
+            shell_args: env(var, "WATCH_SHELL_ARG", |raw| Ok(split_list(raw)))?,
+            chdir: env(var, "WATCH_CHDIR", |raw| Ok(PathBuf::from(raw)))?,
+            env: env(var, "WATCH_ENV", |raw| {
+                split_list(raw).iter().map(|item| parse_env_pair(item)).collect()
+            })?,
+            env_clear: env(var, "WATCH_ENV_CLEAR", parse_bool)?,
+            stdin: env(var, "WATCH_STDIN", |raw| Ok(PathBuf::from(raw)))?,
+            split: env(var, "WATCH_SPLIT", parse_split)?,
+            filter: env(var, "WATCH_FILTER", Pattern::new)?,
+            filter_context: env(var, "WATCH_FILTER_CONTEXT", parse_count)?,
+            filter_invert: env(var, "WATCH_FILTER_INVERT", parse_bool)?,
+            filter_checks: env(var, "WATCH_FILTER_CHECKS", parse_filter_checks)?,
+            notify: env(var, "WATCH_NOTIFY", |raw| {
+                split_list(raw).iter().map(|item| parse_notify_rule(item)).collect()
+            })?,
+            notify_command: env(var, "WATCH_NOTIFY_COMMAND", |raw| Ok(raw.to_string()))?,
+            on_change: env(var, "WATCH_ON_CHANGE", |raw| Ok(raw.to_string()))?,
+            on_fail: env(var, "WATCH_ON_FAIL", |raw| Ok(raw.to_string()))?,
+            on_recover: env(var, "WATCH_ON_RECOVER", |raw| Ok(raw.to_string()))?,
+            hook_gap: env(var, "WATCH_HOOK_GAP", |raw| {
+                parse_duration(raw).map_err(|err| err.to_string())
+            })?,
+            keys: None,
+            command: None,
+        })
+    }
+
+    /// Reads option keys from a config file table. Keys are the long option
+    /// names, e.g. `no-title = true` or `interval = "1m"`.
+    pub fn from_table(table: &Table, context: &str) -> Result<Settings, String> {
+        let mut settings = Settings::default();
+        for (key, value) in table {
+            let at = |err: String| format!("{context}: {key}: {err}");
+            match key.as_str() {
+                "interval" => {
+                    settings.interval = Some(duration(value, parse_interval).map_err(at)?)
+                }
+                "precise" => settings.precise = Some(boolean(value).map_err(at)?),
+                "align" => settings.align = Some(boolean(value).map_err(at)?),
+                "cron" => {
+                    let raw = string(value).map_err(at)?;
+                    settings.cron = Some(parse_cron(&raw).map_err(|err| at(err.to_string()))?);
+                }
+                "backoff" => settings.backoff = Some(duration(value, parse_interval).map_err(at)?),
+                "adaptive" => {
+                    settings.adaptive = Some(duration(value, parse_interval).map_err(at)?);
+                }
+                "no-title" => settings.no_title = Some(boolean(value).map_err(at)?),
+                "no-wrap" => settings.no_wrap = Some(boolean(value).map_err(at)?),
+                "tab-width" => settings.tab_width = Some(integer(value).map_err(at)?),
+                "differences" => {
+                    settings.differences =
+                        Some(differences(&string(value).map_err(at)?).map_err(at)?);
+                }
+                "beep" => settings.beep = Some(boolean(value).map_err(at)?),
+                "beep-on" => {
+                    let conditions = strings(value)
+                        .map_err(at)?
+                        .iter()
+                        .map(|raw| parse_condition(raw))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(at)?;
+                    settings.beep_on = Some(conditions);
+                }
+                "bell-gap" => settings.bell_gap = Some(duration(value, parse_duration).map_err(at)?),
+                "errexit" => settings.errexit = Some(boolean(value).map_err(at)?),
+                "chgexit" => settings.chgexit = Some(boolean(value).map_err(at)?),
+                "equexit" => settings.equexit = Some(integer(value).map_err(at)?),
+                "follow" => settings.follow = Some(boolean(value).map_err(at)?),
+                "separator" => settings.separator = Some(boolean(value).map_err(at)?),
+                "dedup" => settings.dedup = Some(boolean(value).map_err(at)?),
+                "no-rerun" => settings.no_rerun = Some(boolean(value).map_err(at)?),
+                "exec" => settings.exec = Some(boolean(value).map_err(at)?),
+                "color" => settings.color = Some(color(value).map_err(at)?),
+                "shotsdir" => settings.shotsdir = Some(PathBuf::from(string(value).map_err(at)?)),
+                "shot-on" => {
+                    let conditions = strings(value)
+                        .map_err(at)?
+                        .iter()
+                        .map(|raw| parse_condition(raw))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(at)?;
+                    settings.shot_on = Some(conditions);
+                }
+                "shots-keep" => settings.shots_keep = Some(integer(value).map_err(at)?),
+                "watch-path" => {
+                    let paths = strings(value).map_err(at)?;
+                    settings.watch_paths = Some(paths.into_iter().map(PathBuf::from).collect());
+                }
+                "watch-exclude" => settings.watch_excludes = Some(strings(value).map_err(at)?),
+                "watch-debounce" => {
+                    settings.watch_debounce = Some(duration(value, parse_duration).map_err(at)?);
+                }
+                "watch-fallback" => settings.watch_fallback = Some(boolean(value).map_err(at)?),
+                "shell" => settings.shell = Some(string(value).map_err(at)?),
+                "shell-arg" => settings.shell_args = Some(strings(value).map_err(at)?),
+                "chdir" => settings.chdir = Some(PathBuf::from(string(value).map_err(at)?)),
+                "env" => settings.env = Some(env_pairs(value).map_err(at)?),
+                "env-clear" => settings.env_clear = Some(boolean(value).map_err(at)?),
+                "stdin" => settings.stdin = Some(PathBuf::from(string(value).map_err(at)?)),
+                "on-change" => settings.on_change = Some(string(value).map_err(at)?),
+                "on-fail" => settings.on_fail = Some(string(value).map_err(at)?),
+                "on-recover" => settings.on_recover = Some(string(value).map_err(at)?),
+                "hook-gap" => settings.hook_gap = Some(duration(value, parse_duration).map_err(at)?),
+                "notify" => {
+                    let rules = strings(value)
+                        .map_err(at)?
+                        .iter()
+                        .map(|raw| parse_notify_rule(raw))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(at)?;
+                    settings.notify = Some(rules);
+                }
+                "notify-command" => settings.notify_command = Some(string(value).map_err(at)?),
+                "split" => settings.split = Some(parse_split(&string(value).map_err(at)?).map_err(at)?),
+                "filter" => {
+                    settings.filter = Some(Pattern::new(&string(value).map_err(at)?).map_err(at)?);
+                }
+                "filter-context" => settings.filter_context = Some(integer(value).map_err(at)?),
+                "filter-invert" => settings.filter_invert = Some(boolean(value).map_err(at)?),
+                "filter-checks" => {
+                    let raw = string(value).map_err(at)?;
+                    settings.filter_checks = Some(parse_filter_checks(&raw).map_err(at)?);
+                }
+                "keys" => settings.keys = Some(key_bindings(value).map_err(at)?),
+                "command" => settings.command = Some(strings(value).map_err(at)?),
+                _ => return Err(format!("{context}: unknown key '{key}'")),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// A parsed config file: top-level defaults plus named `[profiles.<name>]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigFile {
+    pub defaults: Settings,
+    pub profiles: BTreeMap<String, Settings>,
+}
+
+impl ConfigFile {
+    pub fn parse(text: &str, source: &str) -> Result<Self, String> {
+        let mut table: Table = text
+            .parse()
+            .map_err(|err: toml::de::Error| format!("{source}: {}", err.message()))?;
+
+        let mut profiles = BTreeMap::new();
+        if let Some(value) = table.remove("profiles") {
+            let Value::Table(entries) = value else {
+                return Err(format!("{source}: profiles: expected a table"));
+            };
+            for (name, profile) in entries {
+                let Value::Table(profile) = profile else {
+                    return Err(format!("{source}: profiles.{name}: expected a table"));
+                };
+                let settings =
+                    Settings::from_table(&profile, &format!("{source}: profiles.{name}"))?;
+                profiles.insert(name, settings);
+            }
+        }
+
+        Ok(Self {
+            defaults: Settings::from_table(&table, source)?,
+            profiles,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read config file {}: {err}", path.display()))?;
+        Self::parse(&text, &path.display().to_string())
+    }
+
+    /// Returns the defaults with the named profile applied on top.
+    pub fn resolve(&self, profile: Option<&str>) -> Result<Settings, String> {
+        let Some(name) = profile else {
+            return Ok(self.defaults.clone());
+        };
+        match self.profiles.get(name) {
+            Some(settings) => Ok(self.defaults.clone().merge(settings.clone())),
+            None => Err(format!("unknown profile '{name}'")),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/watch/config.toml`, falling back to `~/.config` (or
+/// `%APPDATA%` on Windows).
+/// Where the config file lives, given the environment `var` reads from.
+pub fn default_config_path(var: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let non_empty_var = |key: &str| var(key).filter(|value| !value.trim().is_empty());
+    let base = non_empty_var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            if cfg!(windows) {
+                non_empty_var("APPDATA").map(PathBuf::from)
+            } else {
+                non_empty_var("HOME").map(|home| PathBuf::from(home).join(".config"))
+            }
+        })?;
+    Some(base.join("watch").join("config.toml"))
+}
+
+/// A `WATCH_*` variable whose value does not parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvError {
+    pub var: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for EnvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.var, self.message)
+    }
+}
+
+impl std::error::Error for EnvError {}
+
+fn env<T>(
+    var: &dyn Fn(&str) -> Option<String>,
+    key: &str,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<Option<T>, EnvError> {
+    var(key)
+        .map(|raw| {
+            parse(raw.trim()).map_err(|message| EnvError {
+                var: key.to_string(),
+                message,
+            })
+        })
+        .transpose()
+}
+
+/// Splits a comma separated environment value into its non-empty items.
+/// `\,` stands for a comma inside an item, e.g. in `match:a{1\,3}`.
+fn split_list(raw: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut chars = raw.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.as_str().starts_with(',') => {
+                item.push(',');
+                chars.next();
+            }
+            ',' => items.push(std::mem::take(&mut item)),
+            _ => item.push(ch),
+        }
+    }
+    items.push(item);
+    items
+        .iter()
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_bool(raw: &str) -> Result<bool, String> {
+    match raw.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(format!("expected a boolean (1/0, true/false, yes/no, on/off), got '{raw}'")),
+    }
+}
+
+fn parse_count<T: std::str::FromStr>(raw: &str) -> Result<T, String> {
+    raw.parse()
+        .map_err(|_| format!("expected a non-negative integer, got '{raw}'"))
+}
+
+/// A switch given on the command line as `--x` or its negation; `None`
+/// when neither was given, leaving the value to the lower layers.
+fn switch(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+fn non_empty<T>(values: Vec<T>) -> Option<Vec<T>> {
+    (!values.is_empty()).then_some(values)
+}
+
+fn boolean(value: &Value) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| "expected true or false".to_string())
+}
+
+fn string(value: &Value) -> Result<String, String> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "expected a string".to_string())
+}
+
+fn strings(value: &Value) -> Result<Vec<String>, String> {
+    match value {
+        Value::String(raw) => Ok(vec![raw.clone()]),
+        Value::Array(items) => items.iter().map(string).collect(),
+        _ => Err("expected a string or an array of strings".to_string()),
+    }
+}
+
+/// Accepts either a table (`env = { RUST_LOG = "debug" }`) or a list of
+/// `KEY=VAL` strings.
+fn env_pairs(value: &Value) -> Result<Vec<(String, String)>, String> {
+    match value {
+        Value::Table(table) => table
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), string(value)?)))
+            .collect(),
+        _ => strings(value)?
+            .iter()
+            .map(|item| parse_env_pair(item))
+            .collect(),
+    }
+}
+
+/// Reads the `[keys]` table, e.g. `quit = ["q", "Ctrl-c"]` or
+/// `top = "g g"`.
+fn key_bindings(value: &Value) -> Result<Vec<KeyBinding>, String> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| "expected a table of actions".to_string())?;
+    table
+        .iter()
+        .map(|(name, value)| {
+            let action = parse_action(name)?;
+            let keys = strings(value)
+                .and_then(|keys| keys.iter().map(|raw| parse_sequence(raw)).collect())
+                .map_err(|err| format!("{name}: {err}"))?;
+            Ok((action, keys))
+        })
+        .collect()
+}
+
+fn integer<T: TryFrom<i64>>(value: &Value) -> Result<T, String> {
+    value
+        .as_integer()
+        .and_then(|raw| T::try_from(raw).ok())
+        .ok_or_else(|| "expected a non-negative integer".to_string())
+}
+
+fn duration<E: std::fmt::Display>(
+    value: &Value,
+    parse: fn(&str) -> Result<Duration, E>,
+) -> Result<Duration, String> {
+    let raw = match value {
+        Value::String(raw) => raw.clone(),
+        Value::Integer(raw) => raw.to_string(),
+        Value::Float(raw) => raw.to_string(),
+        _ => return Err("expected a number of seconds or a duration string".to_string()),
+    };
+    parse(&raw).map_err(|err| err.to_string())
+}
+
+fn differences(raw: &str) -> Result<DifferencesMode, String> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "changes" => Ok(DifferencesMode::Changes),
+        "permanent" => Ok(DifferencesMode::Permanent),
+        _ => Err("expected 'changes' or 'permanent'".to_string()),
+    }
+}
+
+fn color(value: &Value) -> Result<ColorMode, String> {
+    match value.as_bool() {
+        Some(true) => Ok(ColorMode::Always),
+        Some(false) => Ok(ColorMode::Never),
+        None => parse_color(&string(value)?),
+    }
+}
+
+fn parse_color(raw: &str) -> Result<ColorMode, String> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "auto" => Ok(ColorMode::Auto),
+        "always" | "1" | "true" | "yes" | "on" => Ok(ColorMode::Always),
+        "never" | "0" | "false" | "no" | "off" => Ok(ColorMode::Never),
+        _ => Err("expected 'auto', 'always' or 'never'".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Action;
+
+    const SAMPLE: &str = r#"
+interval = 5
+no-title = true
+
+[profiles.pods]
+command = "kubectl get pods"
+interval = "1m"
+differences = "permanent"
+chgexit = true
+"#;
+
+    #[test]
+    fn parses_defaults_and_profiles() {
+        let file = ConfigFile::parse(SAMPLE, "config.toml").unwrap();
+        assert_eq!(file.defaults.interval, Some(Duration::from_secs(5)));
+        let pods = file.resolve(Some("pods")).unwrap();
+        assert_eq!(pods.interval, Some(Duration::from_secs(60)));
+        assert_eq!(pods.no_title, Some(true));
+        assert_eq!(pods.differences, Some(DifferencesMode::Permanent));
+        assert_eq!(pods.command, Some(vec!["kubectl get pods".to_string()]));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_profiles() {
+        let err = ConfigFile::parse("intervall = 5", "config.toml").unwrap_err();
+        assert_eq!(err, "config.toml: unknown key 'intervall'");
+        let file = ConfigFile::parse(SAMPLE, "config.toml").unwrap();
+        assert!(file.resolve(Some("nodes")).unwrap_err().contains("nodes"));
+    }
+
+    #[test]
+    fn reads_key_bindings() {
+        let file = ConfigFile::parse(
+            "[keys]\nquit = [\"Ctrl-q\", \"x x\"]\nrerun = \"r\"\n",
+            "config.toml",
+        )
+        .unwrap();
+        let keys = file.defaults.keys.unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].0, Action::Quit);
+        assert_eq!(keys[0].1[1].len(), 2);
+        let err = ConfigFile::parse("[keys]\nexplode = \"x\"", "config.toml").unwrap_err();
+        assert_eq!(err, "config.toml: keys: unknown action 'explode'");
+        let err = ConfigFile::parse("[keys]\nquit = \"Hyper-q\"", "config.toml").unwrap_err();
+        assert_eq!(err, "config.toml: keys: quit: unknown key 'Hyper-q'");
+    }
+
+    #[test]
+    fn reads_watch_environment_variables() {
+        let vars = [
+            ("WATCH_DIFFERENCES", "permanent"),
+            ("WATCH_COLOR", "always"),
+            ("WATCH_NO_TITLE", "yes"),
+            ("WATCH_SHOT_ON", "fail, change"),
+            ("WATCH_EQUEXIT", ""),
+            ("WATCH_SHELL_ARG", "-NoProfile, -Command"),
+            ("WATCH_BEEP_ON", "match:^a{1\\,3}$,fail"),
+        ];
+        let lookup = |key: &str| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        };
+        let settings = Settings::from_env_with(lookup).unwrap();
+        assert_eq!(settings.differences, Some(DifferencesMode::Permanent));
+        assert_eq!(settings.color, Some(ColorMode::Always));
+        assert_eq!(settings.no_title, Some(true));
+        assert_eq!(settings.shot_on, Some(vec![Condition::Fail, Condition::Change]));
+        assert_eq!(settings.equexit, None);
+        assert_eq!(
+            settings.beep_on,
+            Some(vec![
+                Condition::Match(Pattern::new("^a{1,3}$").unwrap()),
+                Condition::Fail
+            ])
+        );
+        assert_eq!(
+            settings.shell_args,
+            Some(vec!["-NoProfile".to_string(), "-Command".to_string()])
+        );
+    }
+
+    #[test]
+    fn environment_errors_name_the_variable() {
+        let err = Settings::from_env_with(|key| {
+            (key == "WATCH_NO_WRAP").then(|| "maybe".to_string())
+        })
+        .unwrap_err();
+        assert_eq!(err.var, "WATCH_NO_WRAP");
+        assert!(err.to_string().starts_with("WATCH_NO_WRAP: expected a boolean"));
+    }
+
+    #[test]
+    fn reads_env_as_table_or_list() {
+        let file = ConfigFile::parse(
+            "env = { RUST_LOG = \"debug\" }\n[profiles.ci]\nenv = [\"CI=1\"]\n",
+            "config.toml",
+        )
+        .unwrap();
+        let pair = |key: &str, value: &str| (key.to_string(), value.to_string());
+        assert_eq!(file.defaults.env, Some(vec![pair("RUST_LOG", "debug")]));
+        assert_eq!(file.resolve(Some("ci")).unwrap().env, Some(vec![pair("CI", "1")]));
+    }
+
+    #[test]
+    fn later_layers_win() {
+        let file = ConfigFile::parse(SAMPLE, "config.toml").unwrap();
+        let cli = Cli::parse_from_iter(["watch", "-n", "3", "--profile", "pods"]).unwrap();
+        let merged = file
+            .resolve(Some("pods"))
+            .unwrap()
+            .merge(Settings::from_cli(cli).unwrap());
+        assert_eq!(merged.interval, Some(Duration::from_secs(3)));
+        assert_eq!(merged.chgexit, Some(true));
+    }
+
+    #[test]
+    fn later_layers_replace_conflicting_options() {
+        let file = ConfigFile::parse("cron = \"@hourly\"\nprecise = false\n", "config.toml").unwrap();
+        let cli = Cli::parse_from_iter(["watch", "-n", "5", "date"]).unwrap();
+        let merged = file.defaults.clone().merge(Settings::from_cli(cli).unwrap());
+        assert_eq!(merged.cron, None);
+        assert_eq!(merged.interval, Some(Duration::from_secs(5)));
+
+        let file = ConfigFile::parse("interval = 5\nalign = true\n", "config.toml").unwrap();
+        let cli = Cli::parse_from_iter(["watch", "--cron", "@hourly", "date"]).unwrap();
+        let merged = file.defaults.clone().merge(Settings::from_cli(cli).unwrap());
+        assert_eq!((merged.interval, merged.align), (None, None));
+        assert!(merged.cron.is_some());
+    }
+
+    #[test]
+    fn command_line_turns_switches_off() {
+        let file = ConfigFile::parse("errexit = true\nno-title = true\n", "config.toml").unwrap();
+        let cli = Cli::parse_from_iter(["watch", "--no-errexit", "--title", "date"]).unwrap();
+        let merged = file.defaults.clone().merge(Settings::from_cli(cli).unwrap());
+        assert_eq!((merged.errexit, merged.no_title), (Some(false), Some(false)));
+
+        let cli = Cli::parse_from_iter(["watch", "--no-errexit", "-e", "date"]).unwrap();
+        assert_eq!(Settings::from_cli(cli).unwrap().errexit, Some(true));
+    }
+
+    #[test]
+    fn command_line_covers_broken_environment_variables() {
+        let lookup = |key: &str| match key {
+            "WATCH_INTERVAL" => Some("1:30".to_string()),
+            "WATCH_SHELL_ARG" => Some("-c".to_string()),
+            _ => None,
+        };
+        let cli = Cli::parse_from_iter(["watch", "-n", "5", "date"]).unwrap();
+        let cli = Settings::from_cli(cli).unwrap();
+        let env = Settings::from_env_under(lookup, &cli).unwrap();
+        assert_eq!(env.interval, None);
+        assert_eq!(env.shell_args, Some(vec!["-c".to_string()]));
+
+        let err = Settings::from_env_under(lookup, &Settings::default()).unwrap_err();
+        assert_eq!(err.var, "WATCH_INTERVAL");
+    }
+}
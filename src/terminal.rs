@@ -2,11 +2,13 @@ use std::io;
 use std::time::{Duration, Instant};
 
 use crossterm::cursor::{Hide, Show};
-use crossterm::event::{self, Event};
+use crossterm::event::{self, Event, KeyEvent, KeyEventKind};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::execute;
 
-use crate::input::{action_from_event, Action};
+use crate::input::Action;
+use crate::keymap::KeyReader;
+use crate::watcher::FileWatcher;
 
 pub struct TerminalGuard;
 
@@ -28,37 +30,54 @@ impl Drop for TerminalGuard {
 }
 
 pub enum WaitOutcome {
-    Quit,
-    Trigger,
+    /// The action bound to the keys just pressed.
+    Action(Action),
+    /// Files under a watched path changed.
+    FilesChanged,
+    /// A key pressed while typing into a prompt.
+    Key(KeyEvent),
+    Resize,
     Timeout,
 }
 
+/// Waits for a key, a file change or `deadline`. With `raw_keys`, keys are
+/// handed back as they are instead of being mapped to actions by `keys`.
 pub fn wait_for_action(
-    duration: Duration,
-    no_rerun: bool,
-    pending_screenshot: &mut bool,
+    deadline: Instant,
+    mut watcher: Option<&mut FileWatcher>,
+    keys: &mut KeyReader,
+    raw_keys: bool,
 ) -> io::Result<WaitOutcome> {
-    let deadline = Instant::now() + duration;
     loop {
+        if let Some(watcher) = watcher.as_deref_mut()
+            && watcher.poll()
+        {
+            return Ok(WaitOutcome::FilesChanged);
+        }
+
         let now = Instant::now();
         if now >= deadline {
             return Ok(WaitOutcome::Timeout);
         }
 
         let remaining = deadline - now;
-        let poll_for = remaining.min(Duration::from_millis(250));
+        let slice = if watcher.is_some() {
+            Duration::from_millis(50)
+        } else {
+            Duration::from_millis(250)
+        };
+        let poll_for = remaining.min(slice);
         if event::poll(poll_for)? {
             match event::read()? {
-                Event::Resize(_, _) if !no_rerun => return Ok(WaitOutcome::Trigger),
-                event => {
-                    if let Some(action) = action_from_event(event) {
-                        match action {
-                            Action::Quit => return Ok(WaitOutcome::Quit),
-                            Action::Trigger => return Ok(WaitOutcome::Trigger),
-                            Action::Screenshot => *pending_screenshot = true,
-                        }
+                Event::Resize(_, _) => return Ok(WaitOutcome::Resize),
+                Event::Key(key) if key.kind == KeyEventKind::Release => {}
+                Event::Key(key) if raw_keys => return Ok(WaitOutcome::Key(key)),
+                Event::Key(key) => {
+                    if let Some(action) = keys.feed(key) {
+                        return Ok(WaitOutcome::Action(action));
                     }
                 }
+                _ => {}
             }
         }
     }
@@ -0,0 +1,159 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchError {
+    InvalidGlob(String),
+    Watch(String),
+}
+
+impl std::fmt::Display for WatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchError::InvalidGlob(err) => write!(f, "invalid --watch-exclude pattern: {err}"),
+            WatchError::Watch(err) => write!(f, "failed to watch files: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for WatchError {}
+
+/// Watches paths recursively and reports debounced changes.
+pub struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    roots: Vec<PathBuf>,
+    excludes: GlobSet,
+    debounce: Duration,
+    last_change: Option<Instant>,
+}
+
+impl FileWatcher {
+    pub fn new(
+        paths: &[PathBuf],
+        excludes: &[String],
+        debounce: Duration,
+    ) -> Result<Self, WatchError> {
+        let excludes = build_globs(excludes)?;
+        let (tx, events) = channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        })
+        .map_err(|err| WatchError::Watch(err.to_string()))?;
+
+        let mut roots = Vec::with_capacity(paths.len());
+        for path in paths {
+            watcher
+                .watch(path, RecursiveMode::Recursive)
+                .map_err(|err| WatchError::Watch(format!("{}: {err}", path.display())))?;
+            roots.push(std::fs::canonicalize(path).unwrap_or_else(|_| path.clone()));
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            roots,
+            excludes,
+            debounce,
+            last_change: None,
+        })
+    }
+
+    /// Drains queued events and returns `true` once changes have been quiet
+    /// for the debounce period.
+    pub fn poll(&mut self) -> bool {
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            if event.paths.iter().any(|path| !self.is_excluded(path)) {
+                self.last_change = Some(Instant::now());
+            }
+        }
+
+        match self.last_change {
+            Some(at) if at.elapsed() >= self.debounce => {
+                self.last_change = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        if self.excludes.is_match(path) {
+            return true;
+        }
+        self.roots.iter().any(|root| {
+            path.strip_prefix(root)
+                .is_ok_and(|relative| self.excludes.is_match(relative))
+        })
+    }
+}
+
+fn build_globs(patterns: &[String]) -> Result<GlobSet, WatchError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|err| WatchError::InvalidGlob(err.to_string()))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|err| WatchError::InvalidGlob(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("watch-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::canonicalize(dir).unwrap()
+    }
+
+    fn wait_for_change(watcher: &mut FileWatcher, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if watcher.poll() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    #[test]
+    fn rejects_invalid_exclude() {
+        let err = build_globs(&["a[".to_string()]).unwrap_err();
+        assert!(matches!(err, WatchError::InvalidGlob(_)));
+    }
+
+    #[test]
+    fn reports_changes_and_skips_excluded_paths() {
+        let dir = temp_dir("watcher");
+        let mut watcher = FileWatcher::new(
+            std::slice::from_ref(&dir),
+            &["*.swp".to_string()],
+            Duration::from_millis(20),
+        )
+        .unwrap();
+
+        std::fs::write(dir.join("notes.swp"), "x").unwrap();
+        assert!(!wait_for_change(&mut watcher, Duration::from_millis(300)));
+
+        std::fs::write(dir.join("main.rs"), "x").unwrap();
+        let changed = wait_for_change(&mut watcher, Duration::from_secs(2));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(changed);
+    }
+}